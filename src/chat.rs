@@ -0,0 +1,151 @@
+//! Stops broadcasting while the leader types in chat, so followers don't
+//! walk and cast along with every letter.
+//!
+//! Enter or `/` opens the chat and Enter or Escape closes it. The keys that
+//! open and close it are kept from the followers too, so they never open a
+//! chat of their own.
+
+use std::collections::HashSet;
+
+use crate::config::Key;
+use crate::input::KeyEvent;
+
+/// Whether the leader is typing in chat, from the keys it pressed
+#[derive(Debug, Clone, Default)]
+pub struct ChatMode {
+    typing: bool,
+    /// Keys down on the leader, so autorepeat can't open or close the chat again
+    held: HashSet<Key>,
+    /// Held keys that opened or closed the chat, their repeats are kept back too
+    toggled: HashSet<Key>,
+}
+
+impl ChatMode {
+    pub fn is_typing(&self) -> bool {
+        self.typing
+    }
+
+    /// Follows the chat through `event`, and whether the event is broadcast.
+    ///
+    /// Releases always are: they only reach the followers that were sent the press.
+    pub fn filter(&mut self, event: KeyEvent) -> bool {
+        let KeyEvent { key, down } = event;
+        if !down {
+            self.held.remove(&key);
+            self.toggled.remove(&key);
+            return true;
+        }
+
+        let was_typing = self.typing;
+        if self.held.insert(key) {
+            let toggles = matches!(
+                (self.typing, key),
+                (false, Key::VK_RETURN | Key::VK_OEM_2) | (true, Key::VK_RETURN | Key::VK_ESCAPE)
+            );
+            if toggles {
+                self.typing = !self.typing;
+                self.toggled.insert(key);
+            }
+        }
+
+        !was_typing && !self.typing && !self.toggled.contains(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Key::*;
+
+    /// Keys of `events` that go through, with whether the chat is open after each
+    fn run(chat: &mut ChatMode, events: &[KeyEvent]) -> Vec<(KeyEvent, bool)> {
+        let mut broadcast = Vec::new();
+        for event in events {
+            if chat.filter(*event) {
+                broadcast.push((*event, chat.is_typing()));
+            }
+        }
+        broadcast
+    }
+
+    fn tap(key: Key) -> [KeyEvent; 2] {
+        [KeyEvent::down(key), KeyEvent::up(key)]
+    }
+
+    #[test]
+    fn enter_opens_and_closes_the_chat() {
+        let mut chat = ChatMode::default();
+        let events = [
+            tap(VK_1),
+            tap(VK_RETURN),
+            tap(VK_H),
+            tap(VK_I),
+            tap(VK_RETURN),
+            tap(VK_2),
+        ]
+        .concat();
+
+        assert_eq!(
+            run(&mut chat, &events),
+            vec![
+                (KeyEvent::down(VK_1), false),
+                (KeyEvent::up(VK_1), false),
+                // Releases go through, they have nothing to release on the followers
+                (KeyEvent::up(VK_RETURN), true),
+                (KeyEvent::up(VK_H), true),
+                (KeyEvent::up(VK_I), true),
+                (KeyEvent::up(VK_RETURN), false),
+                (KeyEvent::down(VK_2), false),
+                (KeyEvent::up(VK_2), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn slash_opens_and_escape_closes() {
+        let mut chat = ChatMode::default();
+
+        assert!(!chat.filter(KeyEvent::down(VK_OEM_2)));
+        assert!(chat.is_typing());
+        assert!(!chat.filter(KeyEvent::down(VK_OEM_2)));
+        chat.filter(KeyEvent::up(VK_OEM_2));
+        // A slash in the chat does not close it
+        assert!(!chat.filter(KeyEvent::down(VK_OEM_2)));
+        assert!(chat.is_typing());
+
+        assert!(!chat.filter(KeyEvent::down(VK_ESCAPE)));
+        assert!(!chat.is_typing());
+        // Held, the Escape that closed the chat is still kept back
+        assert!(!chat.filter(KeyEvent::down(VK_ESCAPE)));
+        assert!(chat.filter(KeyEvent::up(VK_ESCAPE)));
+        // Escape alone is broadcast as usual
+        assert!(chat.filter(KeyEvent::down(VK_ESCAPE)));
+        assert!(!chat.is_typing());
+    }
+
+    #[test]
+    fn autorepeat_does_not_toggle_the_chat() {
+        let mut chat = ChatMode::default();
+
+        // Held Enter keeps the chat open, as the game does
+        for _ in 0..3 {
+            assert!(!chat.filter(KeyEvent::down(VK_RETURN)));
+            assert!(chat.is_typing());
+        }
+        chat.filter(KeyEvent::up(VK_RETURN));
+        chat.filter(KeyEvent::down(VK_RETURN));
+        assert!(!chat.is_typing());
+        // The closing Enter repeats while held, and none of it goes through
+        for _ in 0..3 {
+            assert!(!chat.filter(KeyEvent::down(VK_RETURN)));
+            assert!(!chat.is_typing());
+        }
+
+        // A key held since before the chat repeats into it, and is kept out
+        assert!(chat.filter(KeyEvent::down(VK_W)));
+        chat.filter(KeyEvent::up(VK_RETURN));
+        chat.filter(KeyEvent::down(VK_RETURN));
+        assert!(!chat.filter(KeyEvent::down(VK_W)));
+        assert!(chat.filter(KeyEvent::up(VK_W)));
+    }
+}
@@ -0,0 +1,356 @@
+//! Keys pressed together with modifiers, like `Alt+1`.
+//!
+//! In the config a chord is written as nested modifiers around a key:
+//! `VK_1`, `Alt(VK_1)`, `Ctrl(Shift(VK_F1))`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::{self, EnumAccess, IntoDeserializer, VariantAccess, Visitor};
+use serde::Deserialize;
+
+use crate::config::Key;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        ctrl: false,
+        alt: false,
+        shift: false,
+    };
+
+    /// Left and right modifier keys
+    pub const KEYS: [Key; 6] = [
+        Key::VK_LCONTROL,
+        Key::VK_RCONTROL,
+        Key::VK_LMENU,
+        Key::VK_RMENU,
+        Key::VK_LSHIFT,
+        Key::VK_RSHIFT,
+    ];
+
+    /// The modifier `key` is itself, if any
+    pub fn of(key: Key) -> Self {
+        use Key::*;
+
+        Self {
+            ctrl: matches!(key, VK_CONTROL | VK_LCONTROL | VK_RCONTROL),
+            alt: matches!(key, VK_MENU | VK_LMENU | VK_RMENU),
+            shift: matches!(key, VK_SHIFT | VK_LSHIFT | VK_RSHIFT),
+        }
+    }
+
+    pub fn union(self, other: Modifiers) -> Self {
+        Self {
+            ctrl: self.ctrl || other.ctrl,
+            alt: self.alt || other.alt,
+            shift: self.shift || other.shift,
+        }
+    }
+
+    pub fn without(self, other: Modifiers) -> Self {
+        Self {
+            ctrl: self.ctrl && !other.ctrl,
+            alt: self.alt && !other.alt,
+            shift: self.shift && !other.shift,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl Chord {
+    pub fn new(modifiers: Modifiers, key: Key) -> Self {
+        Self { modifiers, key }
+    }
+}
+
+impl From<Key> for Chord {
+    fn from(key: Key) -> Self {
+        Self::new(Modifiers::NONE, key)
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.modifiers.ctrl, "Ctrl"),
+            (self.modifiers.alt, "Alt"),
+            (self.modifiers.shift, "Shift"),
+        ] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+/// Binding of `key` pressed while exactly the `held` modifiers are down, or
+/// else the binding of the bare key, which matches whatever is held
+pub fn lookup<T>(bindings: &HashMap<Chord, T>, held: Modifiers, key: Key) -> Option<(&Chord, &T)> {
+    let held = held.without(Modifiers::of(key));
+
+    bindings
+        .get_key_value(&Chord::new(held, key))
+        .or_else(|| bindings.get_key_value(&Chord::from(key)))
+}
+
+/// Modifier keys a follower must release or press around a key, so it sees
+/// exactly the modifiers of the chord it is sent
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Framing {
+    /// Held on the follower but not part of the chord
+    pub lifted: Vec<Key>,
+    /// Part of the chord but not held on the follower
+    pub pressed: Vec<Key>,
+}
+
+impl Framing {
+    /// Framing of `target` for a follower holding the `held` modifier keys
+    pub fn around(held: &[Key], target: Chord) -> Self {
+        use Key::*;
+
+        let lifted = held
+            .iter()
+            .filter(|key| Modifiers::of(**key).without(target.modifiers) != Modifiers::NONE)
+            .copied()
+            .collect::<Vec<_>>();
+        let kept = held
+            .iter()
+            .filter(|key| !lifted.contains(key))
+            .fold(Modifiers::NONE, |kept, key| kept.union(Modifiers::of(*key)));
+
+        let missing = target.modifiers.without(kept);
+        let pressed = [
+            (missing.ctrl, VK_LCONTROL),
+            (missing.alt, VK_LMENU),
+            (missing.shift, VK_LSHIFT),
+        ]
+        .into_iter()
+        .filter_map(|(missing, key)| missing.then_some(key))
+        .collect();
+
+        Self { lifted, pressed }
+    }
+
+    /// Modifier keys down on a follower inside the frame, `held` outside of it
+    pub fn inside(&self, held: &[Key]) -> Vec<Key> {
+        held.iter()
+            .filter(|key| !self.lifted.contains(key))
+            .chain(&self.pressed)
+            .copied()
+            .collect()
+    }
+
+    /// Keystrokes pressing `key` inside the frame, `true` for a key down
+    pub fn press(&self, key: Key) -> Vec<(Key, bool)> {
+        let lift = self.lifted.iter().map(|key| (*key, false));
+        let press = self.pressed.iter().map(|key| (*key, true));
+
+        lift.chain(press).chain([(key, true)]).collect()
+    }
+
+    /// Keystrokes releasing `key` and the frame, lifted keys are only pressed
+    /// again when they are `still_held`
+    pub fn release(&self, key: Key, still_held: &[Key]) -> Vec<(Key, bool)> {
+        let release = self.pressed.iter().rev().map(|key| (*key, false));
+        let restore = self
+            .lifted
+            .iter()
+            .filter(|key| still_held.contains(key))
+            .map(|key| (*key, true));
+
+        [(key, false)]
+            .into_iter()
+            .chain(release)
+            .chain(restore)
+            .collect()
+    }
+}
+
+/// A variant name, read as an identifier rather than a string
+pub(crate) struct Name(pub String);
+
+impl<'de> Deserialize<'de> for Name {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NameVisitor;
+
+        impl<'de> Visitor<'de> for NameVisitor {
+            type Value = Name;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a key or a modifier")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Name, E> {
+                Ok(Name(name.to_string()))
+            }
+        }
+
+        deserializer.deserialize_identifier(NameVisitor)
+    }
+}
+
+struct ChordVisitor;
+
+impl<'de> Visitor<'de> for ChordVisitor {
+    type Value = Chord;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a key, or a key wrapped in Ctrl(..), Alt(..) or Shift(..)"
+        )
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Chord, A::Error> {
+        let (Name(name), variant) = data.variant::<Name>()?;
+        from_variant(&name, variant)
+    }
+}
+
+/// Chord written as the variant `name`, for enums that also take a chord
+pub(crate) fn from_variant<'de, A: VariantAccess<'de>>(
+    name: &str,
+    variant: A,
+) -> Result<Chord, A::Error> {
+    let modifier = match name {
+        "Ctrl" => Modifiers {
+            ctrl: true,
+            ..Modifiers::NONE
+        },
+        "Alt" => Modifiers {
+            alt: true,
+            ..Modifiers::NONE
+        },
+        "Shift" => Modifiers {
+            shift: true,
+            ..Modifiers::NONE
+        },
+        _ => {
+            variant.unit_variant()?;
+            let key = Key::deserialize(name.into_deserializer())
+                .map_err(|e: de::value::Error| de::Error::custom(e))?;
+            return Ok(Chord::from(key));
+        }
+    };
+
+    let chord = variant.newtype_variant::<Chord>()?;
+    Ok(Chord::new(chord.modifiers.union(modifier), chord.key))
+}
+
+impl<'de> Deserialize<'de> for Chord {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_enum("Chord", &[], ChordVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Key::*;
+
+    const ALT: Modifiers = Modifiers {
+        alt: true,
+        ..Modifiers::NONE
+    };
+    const CTRL_SHIFT: Modifiers = Modifiers {
+        ctrl: true,
+        shift: true,
+        alt: false,
+    };
+
+    #[test]
+    fn parses_nested_modifiers() {
+        let chords: Vec<Chord> = ron::from_str("[VK_1, Alt(VK_1), Ctrl(Shift(VK_F1))]").unwrap();
+
+        assert_eq!(
+            chords,
+            vec![
+                Chord::from(VK_1),
+                Chord::new(ALT, VK_1),
+                Chord::new(CTRL_SHIFT, VK_F1)
+            ]
+        );
+        assert_eq!(chords[2].to_string(), "Ctrl+Shift+VK_F1");
+
+        let remap: HashMap<Chord, Chord> = ron::from_str("{ Alt(VK_1): VK_5 }").unwrap();
+        assert_eq!(remap[&Chord::new(ALT, VK_1)], Chord::from(VK_5));
+
+        assert!(ron::from_str::<Chord>("Alt(VK_NOPE)").is_err());
+        assert!(ron::from_str::<Chord>("Meta(VK_1)").is_err());
+    }
+
+    #[test]
+    fn lookup_matches_modifiers_exactly() {
+        let bindings = HashMap::from([
+            (Chord::from(VK_1), "1"),
+            (Chord::new(ALT, VK_1), "alt+1"),
+            (Chord::new(CTRL_SHIFT, VK_1), "ctrl+shift+1"),
+        ]);
+        let found = |held, key| lookup(&bindings, held, key).map(|(_, found)| *found);
+
+        assert_eq!(found(Modifiers::NONE, VK_1), Some("1"));
+        assert_eq!(found(ALT, VK_1), Some("alt+1"));
+        assert_eq!(found(CTRL_SHIFT, VK_1), Some("ctrl+shift+1"));
+        // No Ctrl+Alt+1 binding, and Alt+1 does not match it either
+        assert_eq!(found(Modifiers { ctrl: true, ..ALT }, VK_1), Some("1"));
+        assert_eq!(found(ALT, VK_2), None);
+    }
+
+    #[test]
+    fn a_modifier_does_not_chord_with_itself() {
+        let bindings = HashMap::from([(Chord::from(VK_LMENU), "alt")]);
+
+        assert_eq!(
+            lookup(&bindings, ALT, VK_LMENU).map(|(_, found)| *found),
+            Some("alt")
+        );
+    }
+
+    #[test]
+    fn framing_swaps_modifier_layers() {
+        // Shift+F1 on the leader, the follower wants Ctrl+3
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::NONE
+        };
+        let framing = Framing::around(&[VK_LSHIFT], Chord::new(ctrl, VK_3));
+
+        assert_eq!(framing.lifted, vec![VK_LSHIFT]);
+        assert_eq!(framing.pressed, vec![VK_LCONTROL]);
+        assert_eq!(
+            framing.press(VK_3),
+            vec![(VK_LSHIFT, false), (VK_LCONTROL, true), (VK_3, true)]
+        );
+        assert_eq!(
+            framing.release(VK_3, &[VK_LSHIFT]),
+            vec![(VK_3, false), (VK_LCONTROL, false), (VK_LSHIFT, true)]
+        );
+        // Shift was let go during the chord, it stays up
+        assert_eq!(
+            framing.release(VK_3, &[]),
+            vec![(VK_3, false), (VK_LCONTROL, false)]
+        );
+    }
+
+    #[test]
+    fn framing_keeps_modifiers_already_held() {
+        let framing = Framing::around(&[VK_RCONTROL], Chord::new(CTRL_SHIFT, VK_F1));
+
+        assert_eq!(framing.lifted, Vec::new());
+        assert_eq!(framing.pressed, vec![VK_LSHIFT]);
+        assert_eq!(Framing::around(&[], Chord::from(VK_1)), Framing::default());
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::ops::RangeInclusive;
+use std::path::PathBuf;
 use std::{io::Read, path::Path};
 
 use windows::vk;
@@ -8,6 +9,19 @@ use windows::vk::VIRTUAL_KEY;
 
 use serde::Deserialize;
 
+use crate::chord::Chord;
+use crate::delay::DelayOptions;
+use crate::error::Error;
+use crate::groups::Selector;
+use crate::input::InputMode;
+use crate::layout::{LayoutMonitors, LayoutStrategy};
+use crate::matcher::WindowName;
+use crate::mouse::MouseOptions;
+use crate::profiles::Profile;
+use crate::repeat::RepeatOptions;
+use crate::sequence::Remap;
+use crate::slots::SlotOptions;
+
 // Ignore case
 #[derive(Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
 #[allow(non_camel_case_types)]
@@ -243,6 +257,256 @@ pub enum Key {
     VK_OEM_CLEAR = vk::VK_OEM_CLEAR.0 as isize,
 }
 
+impl Key {
+    /// Every key, in the order of the enum
+    pub const ALL: [Key; 229] = {
+        use Key::*;
+        [
+            VK_0,
+            VK_1,
+            VK_2,
+            VK_3,
+            VK_4,
+            VK_5,
+            VK_6,
+            VK_7,
+            VK_8,
+            VK_9,
+            VK_A,
+            VK_B,
+            VK_C,
+            VK_D,
+            VK_E,
+            VK_F,
+            VK_G,
+            VK_H,
+            VK_I,
+            VK_J,
+            VK_K,
+            VK_L,
+            VK_M,
+            VK_N,
+            VK_O,
+            VK_P,
+            VK_Q,
+            VK_R,
+            VK_S,
+            VK_T,
+            VK_U,
+            VK_V,
+            VK_W,
+            VK_X,
+            VK_Y,
+            VK_Z,
+            VK_ABNT_C1,
+            VK_ABNT_C2,
+            VK__none_,
+            VK_LBUTTON,
+            VK_RBUTTON,
+            VK_CANCEL,
+            VK_MBUTTON,
+            VK_XBUTTON1,
+            VK_XBUTTON2,
+            VK_BACK,
+            VK_TAB,
+            VK_CLEAR,
+            VK_RETURN,
+            VK_SHIFT,
+            VK_CONTROL,
+            VK_MENU,
+            VK_PAUSE,
+            VK_CAPITAL,
+            VK_KANA,
+            VK_IME_ON,
+            VK_JUNJA,
+            VK_FINAL,
+            VK_KANJI,
+            VK_IME_OFF,
+            VK_ESCAPE,
+            VK_CONVERT,
+            VK_NONCONVERT,
+            VK_ACCEPT,
+            VK_MODECHANGE,
+            VK_SPACE,
+            VK_PRIOR,
+            VK_NEXT,
+            VK_END,
+            VK_HOME,
+            VK_LEFT,
+            VK_UP,
+            VK_RIGHT,
+            VK_DOWN,
+            VK_SELECT,
+            VK_PRINT,
+            VK_EXECUTE,
+            VK_SNAPSHOT,
+            VK_INSERT,
+            VK_DELETE,
+            VK_HELP,
+            VK_LWIN,
+            VK_RWIN,
+            VK_APPS,
+            VK_SLEEP,
+            VK_NUMPAD0,
+            VK_NUMPAD1,
+            VK_NUMPAD2,
+            VK_NUMPAD3,
+            VK_NUMPAD4,
+            VK_NUMPAD5,
+            VK_NUMPAD6,
+            VK_NUMPAD7,
+            VK_NUMPAD8,
+            VK_NUMPAD9,
+            VK_MULTIPLY,
+            VK_ADD,
+            VK_SEPARATOR,
+            VK_SUBTRACT,
+            VK_DECIMAL,
+            VK_DIVIDE,
+            VK_F1,
+            VK_F2,
+            VK_F3,
+            VK_F4,
+            VK_F5,
+            VK_F6,
+            VK_F7,
+            VK_F8,
+            VK_F9,
+            VK_F10,
+            VK_F11,
+            VK_F12,
+            VK_F13,
+            VK_F14,
+            VK_F15,
+            VK_F16,
+            VK_F17,
+            VK_F18,
+            VK_F19,
+            VK_F20,
+            VK_F21,
+            VK_F22,
+            VK_F23,
+            VK_F24,
+            VK_NAVIGATION_VIEW,
+            VK_NAVIGATION_MENU,
+            VK_NAVIGATION_UP,
+            VK_NAVIGATION_DOWN,
+            VK_NAVIGATION_LEFT,
+            VK_NAVIGATION_RIGHT,
+            VK_NAVIGATION_ACCEPT,
+            VK_NAVIGATION_CANCEL,
+            VK_NUMLOCK,
+            VK_SCROLL,
+            VK_OEM_NEC_EQUAL,
+            VK_OEM_FJ_MASSHOU,
+            VK_OEM_FJ_TOUROKU,
+            VK_OEM_FJ_LOYA,
+            VK_OEM_FJ_ROYA,
+            VK_LSHIFT,
+            VK_RSHIFT,
+            VK_LCONTROL,
+            VK_RCONTROL,
+            VK_LMENU,
+            VK_RMENU,
+            VK_BROWSER_BACK,
+            VK_BROWSER_FORWARD,
+            VK_BROWSER_REFRESH,
+            VK_BROWSER_STOP,
+            VK_BROWSER_SEARCH,
+            VK_BROWSER_FAVORITES,
+            VK_BROWSER_HOME,
+            VK_VOLUME_MUTE,
+            VK_VOLUME_DOWN,
+            VK_VOLUME_UP,
+            VK_MEDIA_NEXT_TRACK,
+            VK_MEDIA_PREV_TRACK,
+            VK_MEDIA_STOP,
+            VK_MEDIA_PLAY_PAUSE,
+            VK_LAUNCH_MAIL,
+            VK_LAUNCH_MEDIA_SELECT,
+            VK_LAUNCH_APP1,
+            VK_LAUNCH_APP2,
+            VK_OEM_1,
+            VK_OEM_PLUS,
+            VK_OEM_COMMA,
+            VK_OEM_MINUS,
+            VK_OEM_PERIOD,
+            VK_OEM_2,
+            VK_OEM_3,
+            VK_GAMEPAD_A,
+            VK_GAMEPAD_B,
+            VK_GAMEPAD_X,
+            VK_GAMEPAD_Y,
+            VK_GAMEPAD_RIGHT_SHOULDER,
+            VK_GAMEPAD_LEFT_SHOULDER,
+            VK_GAMEPAD_LEFT_TRIGGER,
+            VK_GAMEPAD_RIGHT_TRIGGER,
+            VK_GAMEPAD_DPAD_UP,
+            VK_GAMEPAD_DPAD_DOWN,
+            VK_GAMEPAD_DPAD_LEFT,
+            VK_GAMEPAD_DPAD_RIGHT,
+            VK_GAMEPAD_MENU,
+            VK_GAMEPAD_VIEW,
+            VK_GAMEPAD_LEFT_THUMBSTICK_BUTTON,
+            VK_GAMEPAD_RIGHT_THUMBSTICK_BUTTON,
+            VK_GAMEPAD_LEFT_THUMBSTICK_UP,
+            VK_GAMEPAD_LEFT_THUMBSTICK_DOWN,
+            VK_GAMEPAD_LEFT_THUMBSTICK_RIGHT,
+            VK_GAMEPAD_LEFT_THUMBSTICK_LEFT,
+            VK_GAMEPAD_RIGHT_THUMBSTICK_UP,
+            VK_GAMEPAD_RIGHT_THUMBSTICK_DOWN,
+            VK_GAMEPAD_RIGHT_THUMBSTICK_RIGHT,
+            VK_GAMEPAD_RIGHT_THUMBSTICK_LEFT,
+            VK_OEM_4,
+            VK_OEM_5,
+            VK_OEM_6,
+            VK_OEM_7,
+            VK_OEM_8,
+            VK_OEM_AX,
+            VK_OEM_102,
+            VK_ICO_HELP,
+            VK_ICO_00,
+            VK_PROCESSKEY,
+            VK_ICO_CLEAR,
+            VK_PACKET,
+            VK_OEM_RESET,
+            VK_OEM_JUMP,
+            VK_OEM_PA1,
+            VK_OEM_PA2,
+            VK_OEM_PA3,
+            VK_OEM_WSCTRL,
+            VK_OEM_CUSEL,
+            VK_OEM_ATTN,
+            VK_OEM_FINISH,
+            VK_OEM_COPY,
+            VK_OEM_AUTO,
+            VK_OEM_ENLW,
+            VK_OEM_BACKTAB,
+            VK_ATTN,
+            VK_CRSEL,
+            VK_EXSEL,
+            VK_EREOF,
+            VK_PLAY,
+            VK_ZOOM,
+            VK_NONAME,
+            VK_PA1,
+            VK_OEM_CLEAR,
+        ]
+    };
+}
+
+/// The key of a virtual-key code, or the code back when it is not a `Key`
+impl TryFrom<u16> for Key {
+    type Error = u16;
+
+    fn try_from(code: u16) -> Result<Self, u16> {
+        Key::ALL
+            .into_iter()
+            .find(|key| *key as u16 == code)
+            .ok_or(code)
+    }
+}
+
 impl From<i8> for Key {
     fn from(key: i8) -> Self {
         // Safety: It is unsafe to transmute from i8 to Key,
@@ -252,13 +516,13 @@ impl From<i8> for Key {
     }
 }
 
-impl Into<VIRTUAL_KEY> for Key {
-    fn into(self) -> VIRTUAL_KEY {
-        VIRTUAL_KEY(self as u16)
+impl From<Key> for VIRTUAL_KEY {
+    fn from(key: Key) -> Self {
+        VIRTUAL_KEY(key as u16)
     }
 }
 
-#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Duration {
     Milliseconds(u64),
     Seconds(u64),
@@ -270,9 +534,9 @@ pub enum Duration {
     Years(u64),
 }
 
-impl Into<std::time::Duration> for Duration {
-    fn into(self) -> std::time::Duration {
-        match self {
+impl From<Duration> for std::time::Duration {
+    fn from(duration: Duration) -> Self {
+        match duration {
             Duration::Milliseconds(n) => std::time::Duration::from_millis(n),
             Duration::Seconds(n) => std::time::Duration::from_secs(n),
             Duration::Minutes(n) => std::time::Duration::from_secs(n * 60),
@@ -305,17 +569,26 @@ pub enum Mode {
     Off,
 }
 
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, Debug, Clone, Default)]
 pub enum LayoutOptions {
     Never,
+    #[default]
     Init,
     Always,
 }
 
-impl Default for LayoutOptions {
-    fn default() -> Self {
-        LayoutOptions::Init
-    }
+/// Waits for the game clients to start before taking control of them
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct WaitOptions {
+    /// Minimum number of matching windows
+    #[serde(default = "default_wait_count")]
+    pub count: usize,
+    /// Give up after this long, wait forever when unset
+    #[serde(default)]
+    pub timeout: Option<Duration>,
+    /// Time between two looks at the window list
+    #[serde(default = "default_wait_poll")]
+    pub poll: Duration,
 }
 
 #[derive(serde::Deserialize, Debug, Clone)]
@@ -323,39 +596,124 @@ pub enum Shortcut {
     Mode(Mode),
     Layout,
     Foreground,
+    /// Puts the windows back where they were before we took control
+    Restore,
+    /// Sends the keys without a group of their own to this group only
+    Group(String),
+    /// Sends the keys without a group of their own to every follower again
+    AllFollowers,
+    /// Lifts every key and button the followers were left holding
+    ReleaseAll,
+    /// Makes the window in the next slot the leader
+    NextLeader,
+    /// Makes the window in the previous slot the leader
+    PreviousLeader,
+    /// Makes the window in this slot the leader
+    FocusSlot(usize),
+    /// Shows the borders of the followers if they are hidden, hides them otherwise
+    ToggleBorders,
+    /// Reads the config file again
+    ReloadConfig,
+    /// Pauses or resumes the broadcast, like `pause_key`
+    Pause,
+    /// Puts the windows back and exits
+    Quit,
 }
 
 #[derive(serde::Deserialize, Debug, Clone)]
 pub struct Config {
-    pub window_name: String,
+    /// Part of the title, or a full `MatcherSpec`
+    pub window_name: WindowName,
     #[serde(default)]
     pub layout: LayoutOptions,
+    #[serde(default)]
+    pub layout_strategy: LayoutStrategy,
+    #[serde(default)]
+    pub layout_monitors: LayoutMonitors,
     pub mode: Mode,
     #[serde(default = "std::vec::Vec::new")]
     pub bot_action: Vec<BotAction>,
     #[serde(default)]
     pub mimic_timer: Duration,
+    /// Chord pressed on the leader -> chord sent instead, e.g. `Shift(VK_F1): Ctrl(VK_3)`,
+    /// or keys typed one after another, e.g. `VK_F: Sequence([VK_RETURN, VK_F, VK_RETURN])`.
+    /// The leader must hold exactly the modifiers of the chord, and the follower is
+    /// given exactly the modifiers of the chord it is sent.
     #[serde(default = "default_remap")]
-    pub remap_keybind: HashMap<Key, Key>,
+    pub remap_keybind: HashMap<Chord, Remap>,
     #[serde(default = "default_skip")]
-    pub skip_keybind: Vec<Key>,
+    pub skip_keybind: Vec<Chord>,
+    /// Keys sent as they are, a chord like `Alt(VK_1)` only while its modifiers are held
     #[serde(default = "default_keybind")]
-    pub keybind: Vec<Key>,
+    pub keybind: Vec<Chord>,
+    /// Per-character overrides of the three keybind settings above
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    /// Named subsets of followers, e.g. `"casters": [(title: Some("mage")), (slot: Some(3))]`
+    #[serde(default)]
+    pub groups: HashMap<String, Vec<Selector>>,
+    /// Keys only ever sent to one group, whatever the active group is
+    #[serde(default)]
+    pub group_keys: HashMap<Key, String>,
+    /// Group receiving the other keys at startup, every follower when unset
+    #[serde(default)]
+    pub active_group: Option<String>,
     #[serde(default = "default_shortcuts")]
     pub shortcuts: HashMap<Key, Shortcut>,
+    /// Keys to hold for the shortcuts to fire, none for bare keys. While they
+    /// are held, they and the shortcut keys are kept from the followers.
+    #[serde(default = "default_shortcut_prefix")]
+    pub shortcut_prefix: Vec<Key>,
+    /// How soon a shortcut key can fire again, against key bounce
+    #[serde(default = "default_shortcut_debounce")]
+    pub shortcut_debounce: Duration,
+    /// Where the original window placements are saved, for the `restore` command
+    #[serde(default = "default_state_file")]
+    pub state_file: Option<PathBuf>,
+    #[serde(default)]
+    pub wait: Option<WaitOptions>,
+    /// How often to look for closed or relaunched clients, never when unset
+    #[serde(default = "default_refresh_interval")]
+    pub refresh_interval: Option<Duration>,
+    /// How clients get their slot, slot 0 being the leader
+    #[serde(default)]
+    pub slots: SlotOptions,
+    /// Pause after every message sent to a window
+    #[serde(default)]
+    pub delays: DelayOptions,
+    /// How the keys pressed on the leader are read
+    #[serde(default)]
+    pub input: InputMode,
+    /// Clicks, drags and wheel of the leader mirrored on the followers
+    #[serde(default)]
+    pub mouse: MouseOptions,
+    /// Leader key pausing and resuming the broadcast, never sent to the followers
+    #[serde(default = "default_pause_key")]
+    pub pause_key: Option<Key>,
+    /// Stops broadcasting from Enter or `/` on the leader until Enter or Escape
+    #[serde(default)]
+    pub chat_mode: bool,
+    /// How keys held on the leader repeat on the followers
+    #[serde(default)]
+    pub repeat: RepeatOptions,
 }
 
 impl Config {
     pub fn load<P: AsRef<Path>>(path: P) -> Self {
-        let mut file = std::fs::File::open(path).unwrap();
+        Self::try_load(path).unwrap()
+    }
+
+    pub fn try_load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut file = std::fs::File::open(path).map_err(|e| Error::Config(e.to_string()))?;
         let mut contents = String::new();
-        file.read_to_string(&mut contents).unwrap();
+        file.read_to_string(&mut contents)
+            .map_err(|e| Error::Config(e.to_string()))?;
 
-        ron::from_str(&contents).unwrap()
+        ron::from_str(&contents).map_err(|e| Error::Config(e.to_string()))
     }
 }
 
-pub fn default_keybind() -> Vec<Key> {
+pub fn default_keybind() -> Vec<Chord> {
     pub use Key::*;
 
     let mut keybind = Vec::new();
@@ -368,7 +726,7 @@ pub fn default_keybind() -> Vec<Key> {
     ];
 
     for key in KEYS.iter() {
-        keybind.push(*key);
+        keybind.push((*key).into());
     }
 
     const RANGES: [RangeInclusive<i8>; 5] = [
@@ -381,25 +739,26 @@ pub fn default_keybind() -> Vec<Key> {
 
     for range in RANGES.into_iter() {
         for key in range {
-            keybind.push(key.into());
+            keybind.push(Key::from(key).into());
         }
     }
 
     keybind
 }
 
-pub fn default_remap() -> HashMap<Key, Key> {
+pub fn default_remap() -> HashMap<Chord, Remap> {
     pub use Key::{VK_LMENU, VK_SPACE};
 
-    let default_remap = [(VK_LMENU, VK_SPACE)];
+    let default_remap = [(VK_LMENU.into(), VK_SPACE.into())];
     HashMap::from(default_remap)
 }
 
-pub fn default_skip() -> Vec<Key> {
+pub fn default_skip() -> Vec<Chord> {
     pub use Key::*;
 
     [VK_D, VK_Q, VK_S, VK_SPACE, VK_Z]
         .into_iter()
+        .map(Chord::from)
         .collect::<Vec<_>>()
 }
 
@@ -412,22 +771,81 @@ pub fn default_shortcuts() -> HashMap<Key, Shortcut> {
         (VK_R, Shortcut::Mode(Mode::Mimic)),
         (VK_F, Shortcut::Foreground),
         (VK_L, Shortcut::Layout),
+        (VK_O, Shortcut::Restore),
+        (VK_U, Shortcut::ReleaseAll),
+        (VK_N, Shortcut::NextLeader),
+        (VK_P, Shortcut::PreviousLeader),
+        (VK_F1, Shortcut::FocusSlot(0)),
+        (VK_F2, Shortcut::FocusSlot(1)),
+        (VK_F3, Shortcut::FocusSlot(2)),
+        (VK_F4, Shortcut::FocusSlot(3)),
+        (VK_F5, Shortcut::FocusSlot(4)),
+        (VK_T, Shortcut::ToggleBorders),
+        (VK_C, Shortcut::ReloadConfig),
+        (VK_SPACE, Shortcut::Pause),
+        (VK_Q, Shortcut::Quit),
     ];
     HashMap::from(default_shortcuts)
 }
 
+pub fn default_shortcut_prefix() -> Vec<Key> {
+    vec![Key::VK_LSHIFT, Key::VK_LMENU]
+}
+
+pub fn default_shortcut_debounce() -> Duration {
+    Duration::Milliseconds(50)
+}
+
+pub fn default_wait_count() -> usize {
+    1
+}
+
+pub fn default_wait_poll() -> Duration {
+    Duration::Milliseconds(500)
+}
+
+pub fn default_refresh_interval() -> Option<Duration> {
+    Some(Duration::Seconds(1))
+}
+
+pub fn default_pause_key() -> Option<Key> {
+    Some(Key::VK_SCROLL)
+}
+
+pub fn default_state_file() -> Option<PathBuf> {
+    Some(std::env::temp_dir().join("is_boxer_like_windows.ron"))
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
-            window_name: String::from("warcraft"),
+            window_name: WindowName::from("warcraft"),
             layout: LayoutOptions::Init,
+            layout_strategy: LayoutStrategy::default(),
+            layout_monitors: LayoutMonitors::default(),
             mode: Mode::Mimic,
             bot_action: Vec::new(),
             mimic_timer: Duration::Milliseconds(10),
             remap_keybind: default_remap(),
             skip_keybind: default_skip(),
             keybind: default_keybind(),
+            profiles: Vec::new(),
+            groups: HashMap::new(),
+            group_keys: HashMap::new(),
+            active_group: None,
             shortcuts: default_shortcuts(),
+            shortcut_prefix: default_shortcut_prefix(),
+            shortcut_debounce: default_shortcut_debounce(),
+            state_file: default_state_file(),
+            wait: None,
+            refresh_interval: default_refresh_interval(),
+            slots: SlotOptions::default(),
+            delays: DelayOptions::default(),
+            input: InputMode::default(),
+            mouse: MouseOptions::default(),
+            pause_key: default_pause_key(),
+            chat_mode: false,
+            repeat: RepeatOptions::default(),
         }
     }
 }
@@ -0,0 +1,173 @@
+//! Pauses after every message sent to a window, so the clients do not all
+//! react at the exact same instant.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::config;
+
+/// How long to wait after a message
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delay {
+    Fixed(config::Duration),
+    /// Anything between `min` and `max`, all equally likely
+    Uniform {
+        min: config::Duration,
+        max: config::Duration,
+    },
+    /// Around `mean`, never below `min` nor above `max`
+    Normal {
+        mean: config::Duration,
+        std_dev: config::Duration,
+        min: config::Duration,
+        max: config::Duration,
+    },
+}
+
+impl Default for Delay {
+    fn default() -> Self {
+        Delay::Fixed(config::Duration::Milliseconds(10))
+    }
+}
+
+impl Delay {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Duration {
+        match *self {
+            Delay::Fixed(delay) => delay.into(),
+            Delay::Uniform { min, max } => {
+                let (min, max) = (Duration::from(min), Duration::from(max));
+                if min >= max {
+                    return min;
+                }
+                rng.gen_range(min..=max)
+            }
+            Delay::Normal {
+                mean,
+                std_dev,
+                min,
+                max,
+            } => {
+                // Box-Muller transform
+                let (u1, u2) = (1.0 - rng.gen::<f64>(), rng.gen::<f64>());
+                let z = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+
+                let mean = Duration::from(mean).as_secs_f64();
+                let std_dev = Duration::from(std_dev).as_secs_f64();
+                let seconds = (mean + z * std_dev).max(0.0);
+
+                let (min, max) = (Duration::from(min), Duration::from(max));
+                Duration::from_secs_f64(seconds).clamp(min, max.max(min))
+            }
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DelayOptions {
+    /// Delay of the windows without one of their own
+    #[serde(default)]
+    pub default: Delay,
+    /// Delay of the window in a slot
+    #[serde(default)]
+    pub slots: HashMap<usize, Delay>,
+    /// Draws the same delays on every run when set
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+/// Draws the delays of every window from one random generator
+#[derive(Debug)]
+pub struct Delays {
+    options: DelayOptions,
+    rng: StdRng,
+}
+
+impl Delays {
+    pub fn new(options: DelayOptions) -> Self {
+        let rng = match options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Self { options, rng }
+    }
+
+    /// Next delay of the window in `slot`
+    pub fn next(&mut self, slot: Option<usize>) -> Duration {
+        let delay = slot
+            .and_then(|slot| self.options.slots.get(&slot))
+            .unwrap_or(&self.options.default);
+
+        delay.sample(&mut self.rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Duration::Milliseconds;
+
+    fn options(default: Delay, seed: Option<u64>) -> DelayOptions {
+        DelayOptions {
+            default,
+            slots: HashMap::from([(2, Delay::Fixed(Milliseconds(50)))]),
+            seed,
+        }
+    }
+
+    #[test]
+    fn fixed_and_per_slot() {
+        let mut delays = Delays::new(options(Delay::default(), None));
+
+        assert_eq!(delays.next(None), Duration::from_millis(10));
+        assert_eq!(delays.next(Some(1)), Duration::from_millis(10));
+        assert_eq!(delays.next(Some(2)), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn distributions_stay_in_bounds() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let uniform = Delay::Uniform {
+            min: Milliseconds(5),
+            max: Milliseconds(20),
+        };
+        let normal = Delay::Normal {
+            mean: Milliseconds(15),
+            std_dev: Milliseconds(30),
+            min: Milliseconds(8),
+            max: Milliseconds(25),
+        };
+
+        for _ in 0..1000 {
+            let delay = uniform.sample(&mut rng);
+            assert!(Duration::from_millis(5) <= delay && delay <= Duration::from_millis(20));
+
+            let delay = normal.sample(&mut rng);
+            assert!(Duration::from_millis(8) <= delay && delay <= Duration::from_millis(25));
+        }
+
+        let drawn = (0..100)
+            .map(|_| uniform.sample(&mut rng))
+            .collect::<std::collections::HashSet<_>>();
+        assert!(drawn.len() > 1);
+    }
+
+    #[test]
+    fn seeded_delays_repeat() {
+        let uniform = Delay::Uniform {
+            min: Milliseconds(0),
+            max: Milliseconds(100),
+        };
+        let draw = || {
+            let mut delays = Delays::new(options(uniform, Some(42)));
+            (0..20)
+                .map(|i| delays.next(Some(i % 3)))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(draw(), draw());
+    }
+}
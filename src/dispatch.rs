@@ -0,0 +1,406 @@
+//! Delivers the messages sent to the windows from a thread of its own, so the
+//! loop capturing the leader never waits for the followers.
+//!
+//! Every window has its own queue: a message is posted once it is due and the
+//! pause after the previous message of the same window is over, whatever the
+//! other windows are doing.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use windows::messaging::{
+    WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP,
+    WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
+};
+use windows::{HWND, LPARAM, WPARAM};
+
+use crate::delay::Delays;
+use crate::mouse::MouseButton;
+use crate::platform::Platform;
+
+/// A message to post once `due`
+#[derive(Debug, Clone, Copy)]
+pub struct Job {
+    pub hwnd: HWND,
+    /// Slot of the window, for its delay
+    pub slot: Option<usize>,
+    pub message: u32,
+    pub wparam: WPARAM,
+    pub lparam: LPARAM,
+    pub due: Instant,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DispatchStats {
+    /// Messages waiting to be posted
+    pub queued: usize,
+    /// How late the last message was posted
+    pub lag: Duration,
+    /// Worst lag so far
+    pub max_lag: Duration,
+}
+
+enum Command {
+    Send(Job),
+    /// Answered once every queue is empty
+    Flush(Sender<()>),
+    /// Posts what is due and the releases of what was pressed right away,
+    /// and drops the rest
+    Cancel,
+}
+
+/// What a message presses or releases
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Input {
+    /// Virtual key
+    Key(usize),
+    Button(MouseButton),
+}
+
+/// The key or button `job` presses, `true`, or releases, `false`
+fn input(job: &Job) -> Option<(Input, bool)> {
+    let key = Input::Key(job.wparam.0);
+    let button = |button, down| Some((Input::Button(button), down));
+
+    match job.message {
+        WM_KEYDOWN | WM_SYSKEYDOWN => Some((key, true)),
+        WM_KEYUP | WM_SYSKEYUP => Some((key, false)),
+        WM_LBUTTONDOWN => button(MouseButton::Left, true),
+        WM_LBUTTONUP => button(MouseButton::Left, false),
+        WM_RBUTTONDOWN => button(MouseButton::Right, true),
+        WM_RBUTTONUP => button(MouseButton::Right, false),
+        WM_MBUTTONDOWN => button(MouseButton::Middle, true),
+        WM_MBUTTONUP => button(MouseButton::Middle, false),
+        _ => None,
+    }
+}
+
+/// A key press of a key the window already holds, its previous state bit is set
+fn is_repeat(job: &Job) -> bool {
+    job.lparam.0 & (1 << 30) != 0
+}
+
+pub struct Dispatcher {
+    commands: Option<Sender<Command>>,
+    stats: Arc<Mutex<DispatchStats>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Dispatcher {
+    pub fn new<P: Platform>(platform: P, delays: Delays) -> Self {
+        let (commands, receiver) = channel();
+        let stats = Arc::new(Mutex::new(DispatchStats::default()));
+
+        let thread_stats = stats.clone();
+        let thread = std::thread::spawn(move || run(platform, delays, receiver, thread_stats));
+
+        Self {
+            commands: Some(commands),
+            stats,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn send(&self, job: Job) {
+        self.stats.lock().unwrap().queued += 1;
+        if let Some(commands) = &self.commands {
+            let _ = commands.send(Command::Send(job));
+        }
+    }
+
+    /// Waits until everything sent so far is posted
+    pub fn flush(&self) {
+        let (done, wait) = channel();
+        if let Some(commands) = &self.commands {
+            if commands.send(Command::Flush(done)).is_ok() {
+                let _ = wait.recv();
+            }
+        }
+    }
+
+    /// Stops what is queued: what is due and the releases of what was pressed
+    /// are posted at once, without the pauses in between, and the later
+    /// messages are never posted
+    pub fn cancel(&self) {
+        if let Some(commands) = &self.commands {
+            let _ = commands.send(Command::Cancel);
+        }
+    }
+
+    pub fn stats(&self) -> DispatchStats {
+        *self.stats.lock().unwrap()
+    }
+}
+
+/// Only what is due and the releases of what was pressed are posted before
+/// the thread stops, the rest could be due long after
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        self.commands = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Queue {
+    jobs: VecDeque<(u64, Job)>,
+    /// When the pause after the last posted message is over
+    ready: Instant,
+}
+
+impl Queue {
+    /// When the first job can be posted
+    fn next(&self) -> Option<(Instant, u64)> {
+        self.jobs
+            .front()
+            .map(|(order, job)| (job.due.max(self.ready), *order))
+    }
+}
+
+fn run<P: Platform>(
+    platform: P,
+    mut delays: Delays,
+    commands: Receiver<Command>,
+    stats: Arc<Mutex<DispatchStats>>,
+) {
+    let mut queues: HashMap<isize, Queue> = HashMap::new();
+    let mut flushes: Vec<Sender<()>> = Vec::new();
+    let mut order = 0;
+
+    loop {
+        let next = queues.values().filter_map(Queue::next).min();
+
+        let received = match next {
+            None => commands.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Some((at, _)) => commands.recv_timeout(at.saturating_duration_since(Instant::now())),
+        };
+
+        match received {
+            Ok(Command::Send(job)) => {
+                let queue = queues.entry(job.hwnd.0).or_insert_with(|| Queue {
+                    jobs: VecDeque::new(),
+                    ready: Instant::now(),
+                });
+                queue.jobs.push_back((order, job));
+                order += 1;
+            }
+            Ok(Command::Flush(done)) => flushes.push(done),
+            Ok(Command::Cancel) => cancel(&platform, &mut queues, &stats),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                cancel(&platform, &mut queues, &stats);
+                return;
+            }
+        }
+
+        // Whatever is due, oldest first
+        loop {
+            let now = Instant::now();
+            let due = queues
+                .iter()
+                .filter_map(|(hwnd, queue)| queue.next().map(|next| (next, *hwnd)))
+                .filter(|((at, _), _)| *at <= now)
+                .min();
+            let Some((_, hwnd)) = due else {
+                break;
+            };
+
+            let queue = queues.get_mut(&hwnd).unwrap();
+            let (_, job) = queue.jobs.pop_front().unwrap();
+            platform.post_message(job.hwnd, job.message, job.wparam, job.lparam);
+            queue.ready = Instant::now() + delays.next(job.slot);
+
+            let mut stats = stats.lock().unwrap();
+            stats.queued = stats.queued.saturating_sub(1);
+            stats.lag = now.saturating_duration_since(job.due);
+            stats.max_lag = stats.max_lag.max(stats.lag);
+        }
+
+        if queues.values().all(|queue| queue.jobs.is_empty()) {
+            for done in flushes.drain(..) {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+/// Empties every queue, posting only what is due and the releases of what was
+/// pressed: a release whose press is dropped is dropped with it
+fn cancel<P: Platform>(
+    platform: &P,
+    queues: &mut HashMap<isize, Queue>,
+    stats: &Mutex<DispatchStats>,
+) {
+    let now = Instant::now();
+    let mut cancelled = 0;
+    for queue in queues.values_mut() {
+        let mut dropped: Vec<Input> = Vec::new();
+        for (_, job) in queue.jobs.drain(..) {
+            let due = job.due <= now;
+            let post = match input(&job) {
+                Some((input, true)) => {
+                    dropped.retain(|other| *other != input);
+                    // A repeat is dropped, but the window still holds the key
+                    if !due && !is_repeat(&job) {
+                        dropped.push(input);
+                    }
+                    due
+                }
+                Some((input, false)) => {
+                    let pressed = !dropped.contains(&input);
+                    dropped.retain(|other| *other != input);
+                    pressed
+                }
+                None => due,
+            };
+
+            if post {
+                platform.post_message(job.hwnd, job.message, job.wparam, job.lparam);
+            }
+            cancelled += 1;
+        }
+    }
+
+    let mut stats = stats.lock().unwrap();
+    stats.queued = stats.queued.saturating_sub(cancelled);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Duration::Milliseconds;
+    use crate::delay::{Delay, DelayOptions};
+    use crate::platform::{FakePlatform, PostedMessage};
+
+    fn dispatcher(platform: &FakePlatform) -> Dispatcher {
+        let options = DelayOptions {
+            default: Delay::Fixed(Milliseconds(30)),
+            slots: HashMap::from([(1, Delay::Fixed(Milliseconds(0)))]),
+            seed: None,
+        };
+        Dispatcher::new(platform.clone(), Delays::new(options))
+    }
+
+    fn job(hwnd: isize, slot: usize, message: u32, due: Instant) -> Job {
+        Job {
+            hwnd: HWND(hwnd),
+            slot: Some(slot),
+            message,
+            wparam: WPARAM(0),
+            lparam: LPARAM(0),
+            due,
+        }
+    }
+
+    #[test]
+    fn windows_do_not_wait_for_each_other() {
+        let platform = FakePlatform::new();
+        let dispatcher = dispatcher(&platform);
+        // Everything is queued before the first one is due
+        let due = Instant::now() + Duration::from_millis(200);
+
+        // The slow window gets 3 messages, the fast one 3 as well
+        for message in 0..3 {
+            dispatcher.send(job(1, 0, message, due));
+            dispatcher.send(job(2, 1, message, due));
+        }
+        dispatcher.flush();
+
+        let posted = platform.take_posted();
+        let window = |hwnd| {
+            posted
+                .iter()
+                .filter(|m| m.hwnd == HWND(hwnd))
+                .collect::<Vec<_>>()
+        };
+        let (slow, fast) = (window(1), window(2));
+        let messages =
+            |posted: &[&PostedMessage]| posted.iter().map(|m| m.message).collect::<Vec<_>>();
+        assert_eq!(messages(&slow), vec![0, 1, 2]);
+        assert_eq!(messages(&fast), vec![0, 1, 2]);
+        assert!(posted.iter().all(|m| m.at >= due));
+
+        // The slow window pauses between its messages
+        for pair in slow.windows(2) {
+            assert!(pair[1].at - pair[0].at >= Duration::from_millis(30));
+        }
+        // The fast window was done before the slow one got its second message
+        let position = |m: &PostedMessage| posted.iter().position(|p| p == m).unwrap();
+        assert!(position(fast[2]) < position(slow[1]));
+    }
+
+    #[test]
+    fn waits_until_due_and_reports_lag() {
+        let platform = FakePlatform::new();
+        let dispatcher = dispatcher(&platform);
+        let now = Instant::now();
+
+        dispatcher.send(job(1, 1, 2, now + Duration::from_millis(40)));
+        dispatcher.send(job(1, 1, 1, now));
+        assert_eq!(dispatcher.stats().queued, 2);
+
+        dispatcher.flush();
+        assert!(now.elapsed() >= Duration::from_millis(40));
+        // A window keeps its order, the second message waited for the first
+        let messages = platform
+            .take_posted()
+            .iter()
+            .map(|m| m.message)
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec![2, 1]);
+
+        let stats = dispatcher.stats();
+        assert_eq!(stats.queued, 0);
+        assert!(stats.lag >= Duration::from_millis(40));
+        assert!(stats.max_lag >= stats.lag);
+    }
+
+    #[test]
+    fn only_releases_of_pressed_keys_are_posted_on_drop() {
+        use windows::messaging::{WM_KEYDOWN, WM_LBUTTONDOWN, WM_LBUTTONUP};
+
+        let platform = FakePlatform::new();
+        let dispatcher = dispatcher(&platform);
+        let now = Instant::now();
+        // Far enough that waiting for them would hang the test
+        let later = now + Duration::from_secs(3600);
+        let key = |hwnd, message, key: usize, repeat: bool, due| Job {
+            wparam: WPARAM(key),
+            lparam: LPARAM(if repeat { 1 << 30 } else { 0 }),
+            ..job(hwnd, 0, message, due)
+        };
+
+        // Pressed and released later, neither is posted
+        dispatcher.send(key(1, WM_KEYDOWN, 0x41, false, later));
+        dispatcher.send(key(1, WM_KEYUP, 0x41, false, later));
+        // Pressed already, the release goes out at once
+        dispatcher.send(key(1, WM_KEYDOWN, 0x42, false, now));
+        dispatcher.send(key(1, WM_KEYUP, 0x42, false, later));
+        // A repeat is dropped, the key is still held
+        dispatcher.send(key(1, WM_KEYDOWN, 0x43, true, later));
+        dispatcher.send(key(1, WM_KEYUP, 0x43, false, later));
+        // Buttons pressed before, and not at all
+        dispatcher.send(job(2, 1, WM_LBUTTONUP, later));
+        dispatcher.send(job(2, 1, WM_LBUTTONDOWN, later));
+        dispatcher.send(job(2, 1, WM_LBUTTONUP, later));
+        drop(dispatcher);
+
+        let posted = platform.take_posted();
+        let messages = |hwnd| {
+            posted
+                .iter()
+                .filter(|m| m.hwnd == HWND(hwnd))
+                .map(|m| (m.message, m.wparam))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages(1),
+            vec![(WM_KEYDOWN, 0x42), (WM_KEYUP, 0x42), (WM_KEYUP, 0x43)]
+        );
+        assert_eq!(messages(2), vec![(WM_LBUTTONUP, 0)]);
+    }
+}
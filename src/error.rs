@@ -0,0 +1,46 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The config file can't be read or parsed
+    Config(String),
+    /// An empty `window_name` would match every window on the desktop
+    EmptyWindowName,
+    /// A regex in `window_name` does not compile
+    InvalidWindowName(String),
+    /// No window matches `window_name`
+    NoMatchingWindows { pattern: String },
+    /// Gave up waiting for enough matching windows
+    Timeout {
+        pattern: String,
+        found: usize,
+        wanted: usize,
+    },
+    /// Stopped waiting for windows, the user asked to quit
+    Interrupted,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(e) => write!(f, "Invalid config: {}", e),
+            Error::EmptyWindowName => write!(f, "No window name specified"),
+            Error::InvalidWindowName(e) => write!(f, "Invalid window name: {}", e),
+            Error::NoMatchingWindows { pattern } => {
+                write!(f, "No window matches \"{}\"", pattern)
+            }
+            Error::Timeout {
+                pattern,
+                found,
+                wanted,
+            } => write!(
+                f,
+                "Timed out waiting for windows matching \"{}\" ({}/{} found)",
+                pattern, found, wanted
+            ),
+            Error::Interrupted => write!(f, "Interrupted while waiting for windows"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
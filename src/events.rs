@@ -0,0 +1,32 @@
+use std::fmt;
+
+use windows::HWND;
+
+/// Something changed in the set of windows under control
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowEvent {
+    /// A new matching window showed up
+    Added { hwnd: HWND, title: String },
+    /// A window was closed, or stopped matching
+    Removed { hwnd: HWND, title: String },
+    /// The main window changed because the previous one went away
+    MainChanged { hwnd: Option<HWND> },
+    /// The layout was applied again after the windows changed
+    LaidOut,
+}
+
+impl fmt::Display for WindowEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowEvent::Added { hwnd, title } => write!(f, "Added {:?} \"{}\"", hwnd.0, title),
+            WindowEvent::Removed { hwnd, title } => {
+                write!(f, "Removed {:?} \"{}\"", hwnd.0, title)
+            }
+            WindowEvent::MainChanged { hwnd: Some(hwnd) } => {
+                write!(f, "Main window is {:?}", hwnd.0)
+            }
+            WindowEvent::MainChanged { hwnd: None } => write!(f, "No main window left"),
+            WindowEvent::LaidOut => write!(f, "Windows laid out again"),
+        }
+    }
+}
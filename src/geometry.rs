@@ -0,0 +1,205 @@
+//! Screen geometry, so positions and sizes can't be mixed up with `RECT` corners.
+
+use windows::RECT;
+
+/// A rectangle in screen pixels, from its top-left corner
+#[derive(
+    serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash,
+)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// First column to the right of the rect
+    pub const fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    /// First row below the rect
+    pub const fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+
+    pub const fn area(&self) -> i64 {
+        self.width as i64 * self.height as i64
+    }
+
+    pub fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    pub const fn contains_point(&self, x: i32, y: i32) -> bool {
+        x >= self.x && y >= self.y && x < self.right() && y < self.bottom()
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    /// Moves the rect by the given offset
+    pub const fn translate(&self, dx: i32, dy: i32) -> Rect {
+        Rect::new(self.x + dx, self.y + dy, self.width, self.height)
+    }
+
+    /// Cuts the rect in a top part `height` pixels high and a bottom part
+    pub fn split_top(&self, height: i32) -> (Rect, Rect) {
+        let height = height.clamp(0, self.height);
+        (
+            Rect::new(self.x, self.y, self.width, height),
+            Rect::new(self.x, self.y + height, self.width, self.height - height),
+        )
+    }
+
+    /// Cuts the rect in a left part `width` pixels wide and a right part
+    pub fn split_left(&self, width: i32) -> (Rect, Rect) {
+        let width = width.clamp(0, self.width);
+        (
+            Rect::new(self.x, self.y, width, self.height),
+            Rect::new(self.x + width, self.y, self.width - width, self.height),
+        )
+    }
+
+    /// Shrinks the rect by `amount` pixels on every side, never below an empty rect
+    pub fn inset(&self, amount: i32) -> Rect {
+        let dx = amount.min(self.width / 2);
+        let dy = amount.min(self.height / 2);
+        Rect::new(
+            self.x + dx,
+            self.y + dy,
+            self.width - 2 * dx,
+            self.height - 2 * dy,
+        )
+    }
+
+    /// Scales the size, keeping the top-left corner in place
+    pub fn scale(&self, factor: f32) -> Rect {
+        Rect::new(
+            self.x,
+            self.y,
+            (self.width as f32 * factor).round() as i32,
+            (self.height as f32 * factor).round() as i32,
+        )
+    }
+
+    /// Largest rect with the `width:height` aspect ratio that fits inside, centered
+    pub fn aspect_fit(&self, width: i32, height: i32) -> Rect {
+        if width <= 0 || height <= 0 {
+            return *self;
+        }
+
+        // Compare self.width / self.height with width / height without rounding
+        let (fit_width, fit_height) =
+            if self.width as i64 * height as i64 > width as i64 * self.height as i64 {
+                (
+                    (self.height as i64 * width as i64 / height as i64) as i32,
+                    self.height,
+                )
+            } else {
+                (
+                    self.width,
+                    (self.width as i64 * height as i64 / width as i64) as i32,
+                )
+            };
+
+        Rect::new(
+            self.x + (self.width - fit_width) / 2,
+            self.y + (self.height - fit_height) / 2,
+            fit_width,
+            fit_height,
+        )
+    }
+}
+
+/// `RECT` holds corners: `right`/`bottom` are coordinates, not sizes
+impl From<RECT> for Rect {
+    fn from(rect: RECT) -> Self {
+        Rect::new(
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+        )
+    }
+}
+
+impl From<Rect> for RECT {
+    fn from(rect: Rect) -> Self {
+        RECT {
+            left: rect.x,
+            top: rect.y,
+            right: rect.right(),
+            bottom: rect.bottom(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_corners_to_size() {
+        let win32 = RECT {
+            left: -1080,
+            top: 200,
+            right: 0,
+            bottom: 2120,
+        };
+
+        let rect = Rect::from(win32);
+        assert_eq!(rect, Rect::new(-1080, 200, 1080, 1920));
+        assert_eq!(RECT::from(rect), win32);
+    }
+
+    #[test]
+    fn splits_cover_the_rect() {
+        let rect = Rect::new(10, 20, 300, 200);
+
+        let (top, bottom) = rect.split_top(150);
+        assert_eq!(top, Rect::new(10, 20, 300, 150));
+        assert_eq!(bottom, Rect::new(10, 170, 300, 50));
+
+        let (left, right) = rect.split_left(400);
+        assert_eq!(left, rect);
+        assert_eq!(right.width, 0);
+    }
+
+    #[test]
+    fn inset_and_scale() {
+        let rect = Rect::new(0, 0, 100, 10);
+
+        assert_eq!(rect.inset(4), Rect::new(4, 4, 92, 2));
+        assert_eq!(rect.inset(50), Rect::new(50, 5, 0, 0));
+        assert_eq!(rect.scale(0.5), Rect::new(0, 0, 50, 5));
+    }
+
+    #[test]
+    fn aspect_fit_centers() {
+        let ultrawide = Rect::new(0, 0, 3440, 1440);
+
+        assert_eq!(ultrawide.aspect_fit(16, 9), Rect::new(440, 0, 2560, 1440));
+        assert_eq!(
+            Rect::new(0, 0, 1080, 1920).aspect_fit(16, 9),
+            Rect::new(0, 656, 1080, 607)
+        );
+    }
+}
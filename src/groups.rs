@@ -0,0 +1,158 @@
+//! Broadcast groups: named subsets of followers that only some keys are sent to.
+
+use std::collections::HashMap;
+
+use crate::config::{Config, Key};
+
+/// Picks windows by title and slot, both must match when set
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selector {
+    /// Part of the title (case-insensitive)
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub slot: Option<usize>,
+}
+
+impl Selector {
+    pub fn matches(&self, title: &str, slot: usize) -> bool {
+        selects(self.title.as_deref(), self.slot, title, slot)
+    }
+}
+
+/// Whether a window passes a title filter (case-insensitive) and a slot filter
+pub(crate) fn selects(
+    title_filter: Option<&str>,
+    slot_filter: Option<usize>,
+    title: &str,
+    slot: usize,
+) -> bool {
+    let title_matches =
+        title_filter.is_none_or(|t| title.to_lowercase().contains(&t.to_lowercase()));
+
+    title_matches && slot_filter.is_none_or(|s| s == slot)
+}
+
+/// A follower, as far as groups are concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Member<'a> {
+    pub title: &'a str,
+    pub slot: usize,
+}
+
+/// Group receiving `key`: its own group if it has one, else the active group.
+/// `None` means every follower.
+pub fn key_group<'a>(config: &'a Config, active: Option<&'a str>, key: Key) -> Option<&'a str> {
+    config.group_keys.get(&key).map(String::as_str).or(active)
+}
+
+/// For each window, whether it is in group `name`. An unknown group has no members.
+pub fn in_group(
+    groups: &HashMap<String, Vec<Selector>>,
+    name: &str,
+    windows: &[Member],
+) -> Vec<bool> {
+    let selectors = groups.get(name).map(Vec::as_slice).unwrap_or_default();
+
+    windows
+        .iter()
+        .map(|w| selectors.iter().any(|s| s.matches(w.title, w.slot)))
+        .collect()
+}
+
+/// For each window, whether `key` is sent to it
+pub fn recipients(
+    config: &Config,
+    active: Option<&str>,
+    key: Key,
+    windows: &[Member],
+) -> Vec<bool> {
+    members(config, key_group(config, active, key), windows)
+}
+
+/// For each window, whether it is in `group`. `None` means every window.
+pub fn members(config: &Config, group: Option<&str>, windows: &[Member]) -> Vec<bool> {
+    match group {
+        Some(name) => in_group(&config.groups, name, windows),
+        None => vec![true; windows.len()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Key::*;
+
+    fn config() -> Config {
+        let selector = |title: Option<&str>, slot| Selector {
+            title: title.map(String::from),
+            slot,
+        };
+
+        Config {
+            groups: HashMap::from([
+                (
+                    "casters".to_string(),
+                    vec![selector(Some("mage"), None), selector(None, Some(3))],
+                ),
+                ("melee".to_string(), vec![selector(Some("rogue"), None)]),
+            ]),
+            group_keys: HashMap::from([(VK_4, "melee".to_string())]),
+            ..Config::default()
+        }
+    }
+
+    fn windows() -> Vec<Member<'static>> {
+        vec![
+            Member {
+                title: "WoW - Mage",
+                slot: 1,
+            },
+            Member {
+                title: "WoW - Rogue",
+                slot: 2,
+            },
+            Member {
+                title: "WoW - Priest",
+                slot: 3,
+            },
+        ]
+    }
+
+    #[test]
+    fn every_follower_without_groups() {
+        let config = config();
+
+        assert_eq!(
+            recipients(&config, None, VK_1, &windows()),
+            vec![true, true, true]
+        );
+    }
+
+    #[test]
+    fn active_group_and_key_rules() {
+        let config = config();
+
+        assert_eq!(
+            recipients(&config, Some("casters"), VK_1, &windows()),
+            vec![true, false, true]
+        );
+        // The key rule wins over the active group
+        assert_eq!(
+            recipients(&config, Some("casters"), VK_4, &windows()),
+            vec![false, true, false]
+        );
+        assert_eq!(
+            recipients(&config, None, VK_4, &windows()),
+            vec![false, true, false]
+        );
+    }
+
+    #[test]
+    fn unknown_group_is_empty() {
+        assert_eq!(
+            recipients(&config(), Some("healers"), VK_1, &windows()),
+            vec![false, false, false]
+        );
+    }
+}
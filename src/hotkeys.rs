@@ -0,0 +1,121 @@
+//! Global shortcuts fire once per press of their key, however long it is held
+//! and however often the keys are polled.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::config::Key;
+
+/// Rising edges of the shortcut keys, from one poll of the held keys to the next
+#[derive(Debug, Clone, Default)]
+pub struct Hotkeys {
+    held: HashSet<Key>,
+    /// When each key was last pressed or released
+    changed: HashMap<Key, Instant>,
+}
+
+impl Hotkeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keys of `down` pressed since the last poll while the prefix is held, in
+    /// the order of `down`. A press within `debounce` of the key's last press
+    /// or release is a bounce, and doesn't fire.
+    pub fn pressed(
+        &mut self,
+        now: Instant,
+        down: &[Key],
+        prefix_held: bool,
+        debounce: Duration,
+    ) -> Vec<Key> {
+        let mut pressed = Vec::new();
+        for key in down.iter().filter(|key| !self.held.contains(key)) {
+            let bounced = self
+                .changed
+                .insert(*key, now)
+                .is_some_and(|changed| now.saturating_duration_since(changed) < debounce);
+
+            if prefix_held && !bounced {
+                pressed.push(*key);
+            }
+        }
+        for key in self.held.iter().filter(|key| !down.contains(key)) {
+            self.changed.insert(*key, now);
+        }
+
+        // Keys pressed before the prefix don't fire once it comes
+        self.held = down.iter().copied().collect();
+        pressed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Key::*;
+
+    /// Polls every step of `timeline`, the milliseconds since the start, the
+    /// keys down and whether the prefix is held, and returns what fired when
+    fn run(timeline: &[(u64, &[Key], bool)]) -> Vec<(u64, Key)> {
+        let start = Instant::now();
+        let mut hotkeys = Hotkeys::new();
+
+        timeline
+            .iter()
+            .flat_map(|(ms, down, prefix)| {
+                let now = start + Duration::from_millis(*ms);
+                hotkeys
+                    .pressed(now, down, *prefix, Duration::from_millis(50))
+                    .into_iter()
+                    .map(move |key| (*ms, key))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn held_keys_fire_once() {
+        let held = (0..20)
+            .map(|tick| (tick * 10, &[VK_L][..], true))
+            .collect::<Vec<_>>();
+
+        assert_eq!(run(&held), vec![(0, VK_L)]);
+    }
+
+    #[test]
+    fn every_press_fires_and_bounces_do_not() {
+        assert_eq!(
+            run(&[
+                (0, &[VK_L], true),
+                // Contact bounce right after the press
+                (10, &[], true),
+                (20, &[VK_L], true),
+                (200, &[VK_L], true),
+                // And on release
+                (210, &[], true),
+                (220, &[VK_L], true),
+                (230, &[], true),
+                (300, &[VK_L], true),
+                (310, &[VK_L, VK_R], true),
+                (320, &[], true),
+            ]),
+            vec![(0, VK_L), (300, VK_L), (310, VK_R)]
+        );
+    }
+
+    #[test]
+    fn only_fires_behind_the_prefix() {
+        assert_eq!(
+            run(&[
+                (0, &[VK_L], false),
+                // Already down when the prefix comes
+                (10, &[VK_L], true),
+                (100, &[], true),
+                (200, &[VK_L], true),
+                (300, &[], false),
+                (400, &[VK_R], false),
+            ]),
+            vec![(200, VK_L)]
+        );
+    }
+}
@@ -0,0 +1,440 @@
+//! Where the keys pressed on the leader come from.
+//!
+//! Every source turns what it sees into one stream of `KeyEvent`s, and one of
+//! `MouseEvent`s, for the broadcaster: low-level hooks on Windows, polling the
+//! key states and the cursor when the hooks can't be installed, and a
+//! synthetic source for tests.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::config::Key;
+use crate::mouse::{MouseButton, MouseEvent};
+use crate::platform::Platform;
+
+/// A key of the leader pressed or released. A press of a key that is already
+/// down is autorepeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub down: bool,
+}
+
+impl KeyEvent {
+    pub fn down(key: Key) -> Self {
+        Self { key, down: true }
+    }
+
+    pub fn up(key: Key) -> Self {
+        Self { key, down: false }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputMode {
+    /// Keyboard and mouse hooks, polling where there are no hooks
+    #[default]
+    Hook,
+    Poll,
+}
+
+pub trait InputSource<P: Platform> {
+    /// Events since the last call, oldest first. `watched` are the keys the
+    /// broadcaster cares about, a source seeing every key may ignore it.
+    fn poll(&mut self, platform: &P, watched: &[Key]) -> Vec<KeyEvent>;
+
+    /// Mouse events since the last call, oldest first
+    fn poll_mouse(&mut self, _platform: &P) -> Vec<MouseEvent> {
+        Vec::new()
+    }
+
+    /// Drops the events not read yet. Sources keep queueing while nobody
+    /// polls them, and that backlog is stale once the broadcast resumes.
+    fn discard(&mut self, platform: &P) {
+        self.poll(platform, &[]);
+        self.poll_mouse(platform);
+    }
+}
+
+/// The source for `mode` on `platform`
+pub fn source<P: Platform>(platform: &P, mode: InputMode) -> Box<dyn InputSource<P>> {
+    match mode {
+        InputMode::Hook => platform
+            .input_hook()
+            .unwrap_or_else(|| Box::new(Poller::default())),
+        InputMode::Poll => Box::new(Poller::default()),
+    }
+}
+
+/// Reads the state of the watched keys on every poll. Taps shorter than the
+/// polling interval are missed, and held keys repeat at the polling rate.
+///
+/// The mouse is read the same way, without its wheel, which has no state.
+#[derive(Debug, Default)]
+pub struct Poller {
+    down: HashSet<Key>,
+    buttons: HashSet<MouseButton>,
+    cursor: Option<(i32, i32)>,
+}
+
+impl<P: Platform> InputSource<P> for Poller {
+    fn poll(&mut self, platform: &P, watched: &[Key]) -> Vec<KeyEvent> {
+        let mut events = Vec::new();
+        for key in watched {
+            if platform.is_key_down(*key) {
+                self.down.insert(*key);
+                events.push(KeyEvent::down(*key));
+            } else if self.down.remove(key) {
+                events.push(KeyEvent::up(*key));
+            }
+        }
+
+        events
+    }
+
+    fn poll_mouse(&mut self, platform: &P) -> Vec<MouseEvent> {
+        let mut events = Vec::new();
+        let (x, y) = platform.cursor_position();
+        if self.cursor.is_some_and(|cursor| cursor != (x, y)) {
+            events.push(MouseEvent::Move { x, y });
+        }
+        self.cursor = Some((x, y));
+
+        for button in MouseButton::ALL {
+            let down = platform.is_key_down(button.key());
+            let changed = if down {
+                self.buttons.insert(button)
+            } else {
+                self.buttons.remove(&button)
+            };
+            if changed {
+                events.push(MouseEvent::Button { button, down, x, y });
+            }
+        }
+
+        events
+    }
+}
+
+/// Events pushed by hand. Clones share the same queue, so a test can keep one
+/// to type on while the `App` reads from the other.
+#[derive(Debug, Clone, Default)]
+pub struct SyntheticInput {
+    events: Arc<Mutex<VecDeque<KeyEvent>>>,
+    mouse: Arc<Mutex<VecDeque<MouseEvent>>>,
+}
+
+impl SyntheticInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&self, event: KeyEvent) {
+        self.events.lock().unwrap().push_back(event);
+    }
+
+    pub fn press(&self, key: Key) {
+        self.push(KeyEvent::down(key));
+    }
+
+    pub fn release(&self, key: Key) {
+        self.push(KeyEvent::up(key));
+    }
+
+    /// Press and release, before anyone reads them
+    pub fn tap(&self, key: Key) {
+        self.press(key);
+        self.release(key);
+    }
+
+    pub fn push_mouse(&self, event: MouseEvent) {
+        self.mouse.lock().unwrap().push_back(event);
+    }
+
+    /// Presses and releases `button` at `x, y`, in screen coordinates
+    pub fn click(&self, button: MouseButton, x: i32, y: i32) {
+        self.push_mouse(MouseEvent::Button {
+            button,
+            down: true,
+            x,
+            y,
+        });
+        self.push_mouse(MouseEvent::Button {
+            button,
+            down: false,
+            x,
+            y,
+        });
+    }
+}
+
+impl<P: Platform> InputSource<P> for SyntheticInput {
+    fn poll(&mut self, _platform: &P, _watched: &[Key]) -> Vec<KeyEvent> {
+        self.events.lock().unwrap().drain(..).collect()
+    }
+
+    fn poll_mouse(&mut self, _platform: &P) -> Vec<MouseEvent> {
+        self.mouse.lock().unwrap().drain(..).collect()
+    }
+}
+
+#[cfg(windows)]
+pub use hook::InputHook;
+
+#[cfg(windows)]
+mod hook {
+    use std::sync::mpsc::{channel, Receiver, Sender};
+    use std::sync::Mutex;
+    use std::thread::JoinHandle;
+
+    use windows::hooks::{
+        CallNextHookEx, GetMessage, PostThreadMessage, SetWindowsHookEx, UnhookWindowsHookEx,
+        HC_ACTION, HHOOK, HMODULE, KBDLLHOOKSTRUCT, LLKHF_INJECTED, LLMHF_INJECTED, LRESULT, MSG,
+        MSLLHOOKSTRUCT, WH_KEYBOARD_LL, WH_MOUSE_LL, WM_QUIT,
+    };
+    use windows::messaging::{
+        WM_KEYDOWN, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEMOVE,
+        WM_MOUSEWHEEL, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN,
+    };
+    use windows::threading::GetCurrentThreadId;
+    use windows::{HWND, LPARAM, WPARAM};
+
+    use super::{InputSource, KeyEvent};
+    use crate::config::Key;
+    use crate::mouse::{MouseButton, MouseEvent};
+    use crate::platform::Platform;
+
+    struct Senders {
+        keys: Sender<KeyEvent>,
+        mouse: Sender<MouseEvent>,
+    }
+
+    /// Where the hook procedures send their events, there is one hook at a time
+    static EVENTS: Mutex<Option<Senders>> = Mutex::new(None);
+
+    unsafe extern "system" fn keyboard_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        if code == HC_ACTION as i32 {
+            let info = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+            // Keys typed by other programs are not the leader's
+            let injected = info.flags.0 & LLKHF_INJECTED.0 != 0;
+
+            if let (false, Ok(key)) = (injected, Key::try_from(info.vkCode as u16)) {
+                let down = matches!(wparam.0 as u32, WM_KEYDOWN | WM_SYSKEYDOWN);
+                if let Some(events) = EVENTS.lock().unwrap().as_ref() {
+                    let _ = events.keys.send(KeyEvent { key, down });
+                }
+            }
+        }
+
+        CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+    }
+
+    unsafe extern "system" fn mouse_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        if code == HC_ACTION as i32 {
+            let info = &*(lparam.0 as *const MSLLHOOKSTRUCT);
+            let (x, y) = (info.pt.x, info.pt.y);
+            let button = |button, down| Some(MouseEvent::Button { button, down, x, y });
+
+            let event = match wparam.0 as u32 {
+                _ if info.flags & LLMHF_INJECTED != 0 => None,
+                WM_MOUSEMOVE => Some(MouseEvent::Move { x, y }),
+                WM_LBUTTONDOWN => button(MouseButton::Left, true),
+                WM_LBUTTONUP => button(MouseButton::Left, false),
+                WM_RBUTTONDOWN => button(MouseButton::Right, true),
+                WM_RBUTTONUP => button(MouseButton::Right, false),
+                WM_MBUTTONDOWN => button(MouseButton::Middle, true),
+                WM_MBUTTONUP => button(MouseButton::Middle, false),
+                WM_MOUSEWHEEL => Some(MouseEvent::Wheel {
+                    delta: (info.mouseData >> 16) as u16 as i16,
+                    x,
+                    y,
+                }),
+                _ => None,
+            };
+
+            if let (Some(event), Some(events)) = (event, EVENTS.lock().unwrap().as_ref()) {
+                let _ = events.mouse.send(event);
+            }
+        }
+
+        CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+    }
+
+    /// Low-level keyboard and mouse hooks, seeing every key and click as it happens
+    pub struct InputHook {
+        keys: Receiver<KeyEvent>,
+        mouse: Receiver<MouseEvent>,
+        thread_id: u32,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl InputHook {
+        /// Installs the hooks on a thread running the message loop they need.
+        /// `None` when the hooks are already installed or the system refuses them.
+        pub fn install() -> Option<Self> {
+            let (keys_sender, keys) = channel();
+            let (mouse_sender, mouse) = channel();
+            {
+                let mut installed = EVENTS.lock().unwrap();
+                if installed.is_some() {
+                    return None;
+                }
+                *installed = Some(Senders {
+                    keys: keys_sender,
+                    mouse: mouse_sender,
+                });
+            }
+
+            let (started, start) = channel();
+            let thread = std::thread::spawn(move || unsafe {
+                let keyboard =
+                    SetWindowsHookEx(WH_KEYBOARD_LL, Some(keyboard_proc), HMODULE::default(), 0);
+                let mouse = SetWindowsHookEx(WH_MOUSE_LL, Some(mouse_proc), HMODULE::default(), 0);
+                let (keyboard, mouse) = match (keyboard, mouse) {
+                    (Ok(keyboard), Ok(mouse)) => (keyboard, mouse),
+                    (keyboard, mouse) => {
+                        for hook in [keyboard, mouse].into_iter().flatten() {
+                            UnhookWindowsHookEx(hook);
+                        }
+                        let _ = started.send(None);
+                        return;
+                    }
+                };
+                let _ = started.send(Some(GetCurrentThreadId()));
+
+                let mut message = MSG::default();
+                while GetMessage(&mut message, HWND::default(), 0, 0).0 > 0 {}
+                UnhookWindowsHookEx(keyboard);
+                UnhookWindowsHookEx(mouse);
+            });
+
+            match start.recv().ok().flatten() {
+                Some(thread_id) => Some(Self {
+                    keys,
+                    mouse,
+                    thread_id,
+                    thread: Some(thread),
+                }),
+                None => {
+                    let _ = thread.join();
+                    *EVENTS.lock().unwrap() = None;
+                    None
+                }
+            }
+        }
+    }
+
+    impl<P: Platform> InputSource<P> for InputHook {
+        fn poll(&mut self, _platform: &P, _watched: &[Key]) -> Vec<KeyEvent> {
+            self.keys.try_iter().collect()
+        }
+
+        fn poll_mouse(&mut self, _platform: &P) -> Vec<MouseEvent> {
+            self.mouse.try_iter().collect()
+        }
+    }
+
+    impl Drop for InputHook {
+        fn drop(&mut self) {
+            unsafe {
+                PostThreadMessage(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+            }
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+            *EVENTS.lock().unwrap() = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::FakePlatform;
+    use Key::*;
+
+    #[test]
+    fn poller_repeats_held_keys_and_reports_releases_once() {
+        let platform = FakePlatform::new();
+        let mut poller = Poller::default();
+        let watched = [VK_1, VK_2];
+        let mut poll = || InputSource::<FakePlatform>::poll(&mut poller, &platform, &watched);
+
+        assert_eq!(poll(), vec![]);
+        platform.press(VK_1);
+        assert_eq!(poll(), vec![KeyEvent::down(VK_1)]);
+        assert_eq!(poll(), vec![KeyEvent::down(VK_1)]);
+        platform.release(VK_1);
+        platform.press(VK_2);
+        assert_eq!(poll(), vec![KeyEvent::up(VK_1), KeyEvent::down(VK_2)]);
+        platform.release(VK_2);
+        assert_eq!(poll(), vec![KeyEvent::up(VK_2)]);
+        assert_eq!(poll(), vec![]);
+
+        // A tap between two polls is never seen
+        platform.press(VK_1);
+        platform.release(VK_1);
+        assert_eq!(poll(), vec![]);
+    }
+
+    #[test]
+    fn poller_reports_moves_and_button_changes() {
+        let platform = FakePlatform::new();
+        let mut poller = Poller::default();
+        let mut poll = || InputSource::<FakePlatform>::poll_mouse(&mut poller, &platform);
+
+        platform.set_cursor_position(10, 20);
+        assert_eq!(poll(), vec![]);
+
+        platform.press(VK_RBUTTON);
+        assert_eq!(
+            poll(),
+            vec![MouseEvent::Button {
+                button: MouseButton::Right,
+                down: true,
+                x: 10,
+                y: 20
+            }]
+        );
+        // A held button is not pressed again
+        platform.set_cursor_position(15, 20);
+        assert_eq!(poll(), vec![MouseEvent::Move { x: 15, y: 20 }]);
+
+        platform.release(VK_RBUTTON);
+        assert_eq!(
+            poll(),
+            vec![MouseEvent::Button {
+                button: MouseButton::Right,
+                down: false,
+                x: 15,
+                y: 20
+            }]
+        );
+    }
+
+    #[test]
+    fn synthetic_input_keeps_taps() {
+        let platform = FakePlatform::new();
+        let typed = SyntheticInput::new();
+        let mut input = typed.clone();
+
+        typed.tap(VK_1);
+        typed.press(VK_2);
+        assert_eq!(
+            input.poll(&platform, &[]),
+            vec![
+                KeyEvent::down(VK_1),
+                KeyEvent::up(VK_1),
+                KeyEvent::down(VK_2)
+            ]
+        );
+        assert_eq!(input.poll(&platform, &[]), vec![]);
+    }
+
+    #[test]
+    fn virtual_key_codes() {
+        assert_eq!(Key::try_from(0x31u16), Ok(VK_1));
+        assert_eq!(Key::try_from(VK_LMENU as u16), Ok(VK_LMENU));
+        assert_eq!(Key::try_from(0x07u16), Err(0x07));
+    }
+}
@@ -0,0 +1,319 @@
+//! `lParam` of keystroke messages, built the way the system builds it for real key presses.
+//!
+//! https://learn.microsoft.com/en-us/windows/win32/inputdev/about-keyboard-input#keystroke-message-flags
+
+use windows::messaging::{WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN, WM_SYSKEYUP};
+use windows::LPARAM;
+
+use crate::chord::Modifiers;
+use crate::config::Key;
+
+const EXTENDED: u32 = 1 << 24;
+const CONTEXT: u32 = 1 << 29;
+const PREVIOUS_STATE: u32 = 1 << 30;
+const TRANSITION: u32 = 1 << 31;
+
+/// Flags of a `WM_KEYDOWN`, `WM_KEYUP`, `WM_SYSKEYDOWN` or `WM_SYSKEYUP` message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyLParam {
+    /// Times the keystroke is repeated because the key is held, always 1 for a release
+    pub repeat_count: u16,
+    /// Hardware scan code, 0 when unknown
+    pub scan_code: u8,
+    /// Right-hand Ctrl/Alt, navigation cluster, numpad divide...
+    pub extended: bool,
+    /// Alt is held
+    pub context: bool,
+    /// The key was already down before this message
+    pub previous_state: bool,
+    /// The key is being released
+    pub transition: bool,
+}
+
+impl KeyLParam {
+    /// Press of `key`, `repeat` when it was already held (autorepeat)
+    pub fn key_down(key: Key, repeat: bool) -> Self {
+        Self {
+            repeat_count: 1,
+            scan_code: scan_code(key),
+            extended: is_extended(key),
+            context: false,
+            previous_state: repeat,
+            transition: false,
+        }
+    }
+
+    /// Release of `key`, which is always down before
+    pub fn key_up(key: Key) -> Self {
+        Self {
+            previous_state: true,
+            transition: true,
+            ..Self::key_down(key, true)
+        }
+    }
+
+    /// Sets the context code, for keystrokes made while Alt is held
+    pub fn with_context(self, alt_down: bool) -> Self {
+        Self {
+            context: alt_down,
+            ..self
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        let mut bits = self.repeat_count as u32 | ((self.scan_code as u32) << 16);
+        for (set, flag) in [
+            (self.extended, EXTENDED),
+            (self.context, CONTEXT),
+            (self.previous_state, PREVIOUS_STATE),
+            (self.transition, TRANSITION),
+        ] {
+            if set {
+                bits |= flag;
+            }
+        }
+
+        bits
+    }
+}
+
+/// The message carries 32 bits, zero-extended on 64-bit systems
+impl From<KeyLParam> for LPARAM {
+    fn from(lparam: KeyLParam) -> Self {
+        LPARAM(lparam.bits() as isize)
+    }
+}
+
+/// Message and lParam for `key`, with `modifiers` down on the receiving window.
+///
+/// Like a real keyboard, keys typed while Alt is held (and Ctrl is not) become
+/// `WM_SYSKEYDOWN`/`WM_SYSKEYUP` with the context code set, and so does F10.
+pub fn keystroke(key: Key, down: bool, repeat: bool, modifiers: Modifiers) -> (u32, KeyLParam) {
+    let alt = modifiers.alt && !modifiers.ctrl;
+    let system = alt || key == Key::VK_F10;
+
+    let message = match (system, down) {
+        (false, true) => WM_KEYDOWN,
+        (false, false) => WM_KEYUP,
+        (true, true) => WM_SYSKEYDOWN,
+        (true, false) => WM_SYSKEYUP,
+    };
+    let lparam = if down {
+        KeyLParam::key_down(key, repeat)
+    } else {
+        KeyLParam::key_up(key)
+    };
+
+    (message, lparam.with_context(alt))
+}
+
+/// Set 1 scan code of the key on a US keyboard, 0 for keys without one
+pub fn scan_code(key: Key) -> u8 {
+    use Key::*;
+
+    match key {
+        VK_ESCAPE => 0x01,
+        VK_1 => 0x02,
+        VK_2 => 0x03,
+        VK_3 => 0x04,
+        VK_4 => 0x05,
+        VK_5 => 0x06,
+        VK_6 => 0x07,
+        VK_7 => 0x08,
+        VK_8 => 0x09,
+        VK_9 => 0x0A,
+        VK_0 => 0x0B,
+        VK_OEM_MINUS => 0x0C,
+        VK_OEM_PLUS => 0x0D,
+        VK_BACK => 0x0E,
+        VK_TAB => 0x0F,
+        VK_Q => 0x10,
+        VK_W => 0x11,
+        VK_E => 0x12,
+        VK_R => 0x13,
+        VK_T => 0x14,
+        VK_Y => 0x15,
+        VK_U => 0x16,
+        VK_I => 0x17,
+        VK_O => 0x18,
+        VK_P => 0x19,
+        VK_OEM_4 => 0x1A,
+        VK_OEM_6 => 0x1B,
+        VK_RETURN => 0x1C,
+        VK_CONTROL | VK_LCONTROL | VK_RCONTROL => 0x1D,
+        VK_A => 0x1E,
+        VK_S => 0x1F,
+        VK_D => 0x20,
+        VK_F => 0x21,
+        VK_G => 0x22,
+        VK_H => 0x23,
+        VK_J => 0x24,
+        VK_K => 0x25,
+        VK_L => 0x26,
+        VK_OEM_1 => 0x27,
+        VK_OEM_7 => 0x28,
+        VK_OEM_3 => 0x29,
+        VK_SHIFT | VK_LSHIFT => 0x2A,
+        VK_OEM_5 => 0x2B,
+        VK_Z => 0x2C,
+        VK_X => 0x2D,
+        VK_C => 0x2E,
+        VK_V => 0x2F,
+        VK_B => 0x30,
+        VK_N => 0x31,
+        VK_M => 0x32,
+        VK_OEM_COMMA => 0x33,
+        VK_OEM_PERIOD => 0x34,
+        VK_OEM_2 | VK_DIVIDE => 0x35,
+        VK_RSHIFT => 0x36,
+        VK_MULTIPLY | VK_SNAPSHOT => 0x37,
+        VK_MENU | VK_LMENU | VK_RMENU => 0x38,
+        VK_SPACE => 0x39,
+        VK_CAPITAL => 0x3A,
+        VK_F1 => 0x3B,
+        VK_F2 => 0x3C,
+        VK_F3 => 0x3D,
+        VK_F4 => 0x3E,
+        VK_F5 => 0x3F,
+        VK_F6 => 0x40,
+        VK_F7 => 0x41,
+        VK_F8 => 0x42,
+        VK_F9 => 0x43,
+        VK_F10 => 0x44,
+        VK_NUMLOCK | VK_PAUSE => 0x45,
+        VK_SCROLL => 0x46,
+        VK_NUMPAD7 | VK_HOME => 0x47,
+        VK_NUMPAD8 | VK_UP => 0x48,
+        VK_NUMPAD9 | VK_PRIOR => 0x49,
+        VK_SUBTRACT => 0x4A,
+        VK_NUMPAD4 | VK_LEFT => 0x4B,
+        VK_NUMPAD5 | VK_CLEAR => 0x4C,
+        VK_NUMPAD6 | VK_RIGHT => 0x4D,
+        VK_ADD => 0x4E,
+        VK_NUMPAD1 | VK_END => 0x4F,
+        VK_NUMPAD2 | VK_DOWN => 0x50,
+        VK_NUMPAD3 | VK_NEXT => 0x51,
+        VK_NUMPAD0 | VK_INSERT => 0x52,
+        VK_DECIMAL | VK_DELETE => 0x53,
+        VK_OEM_102 => 0x56,
+        VK_F11 => 0x57,
+        VK_F12 => 0x58,
+        VK_LWIN => 0x5B,
+        VK_RWIN => 0x5C,
+        VK_APPS => 0x5D,
+        VK_F13 => 0x64,
+        VK_F14 => 0x65,
+        VK_F15 => 0x66,
+        VK_F16 => 0x67,
+        VK_F17 => 0x68,
+        VK_F18 => 0x69,
+        VK_F19 => 0x6A,
+        VK_F20 => 0x6B,
+        VK_F21 => 0x6C,
+        VK_F22 => 0x6D,
+        VK_F23 => 0x6E,
+        VK_F24 => 0x76,
+        _ => 0,
+    }
+}
+
+/// Keys sharing a scan code with a numpad key, or sent with an `E0` prefix.
+/// Right Shift has a scan code of its own and is not extended.
+pub fn is_extended(key: Key) -> bool {
+    use Key::*;
+
+    matches!(
+        key,
+        VK_RCONTROL
+            | VK_RMENU
+            | VK_INSERT
+            | VK_DELETE
+            | VK_HOME
+            | VK_END
+            | VK_PRIOR
+            | VK_NEXT
+            | VK_LEFT
+            | VK_UP
+            | VK_RIGHT
+            | VK_DOWN
+            | VK_NUMLOCK
+            | VK_DIVIDE
+            | VK_SNAPSHOT
+            | VK_LWIN
+            | VK_RWIN
+            | VK_APPS
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Key::*;
+
+    #[test]
+    fn first_press_and_release() {
+        // Scan code 0x1E, repeat count 1
+        assert_eq!(KeyLParam::key_down(VK_A, false).bits(), 0x001E_0001);
+        assert_eq!(KeyLParam::key_up(VK_A).bits(), 0xC01E_0001);
+    }
+
+    #[test]
+    fn autorepeat_sets_previous_state() {
+        let lparam = KeyLParam::key_down(VK_1, true);
+
+        assert!(lparam.previous_state);
+        assert!(!lparam.transition);
+        assert_eq!(lparam.bits(), 0x4002_0001);
+    }
+
+    #[test]
+    fn extended_keys() {
+        assert_eq!(KeyLParam::key_down(VK_LEFT, false).bits(), 0x014B_0001);
+        assert_eq!(KeyLParam::key_down(VK_NUMPAD4, false).bits(), 0x004B_0001);
+        assert_eq!(KeyLParam::key_up(VK_RCONTROL).bits(), 0xC11D_0001);
+        assert!(!is_extended(VK_RSHIFT));
+        assert_eq!(scan_code(VK_RSHIFT), 0x36);
+    }
+
+    #[test]
+    fn context_code_and_lparam() {
+        let lparam = KeyLParam::key_down(VK_F4, false).with_context(true);
+
+        assert_eq!(lparam.bits(), 0x203E_0001);
+        assert_eq!(LPARAM::from(KeyLParam::key_up(VK_A)), LPARAM(0xC01E_0001));
+    }
+
+    #[test]
+    fn alt_makes_system_keystrokes() {
+        let alt = Modifiers {
+            alt: true,
+            ..Modifiers::NONE
+        };
+
+        let (message, lparam) = keystroke(VK_1, true, false, alt);
+        assert_eq!((message, lparam.bits()), (WM_SYSKEYDOWN, 0x2002_0001));
+        let (message, lparam) = keystroke(VK_LMENU, false, false, alt);
+        assert_eq!((message, lparam.bits()), (WM_SYSKEYUP, 0xE038_0001));
+
+        // AltGr is Ctrl+Alt, which types regular keys
+        let alt_gr = Modifiers { ctrl: true, ..alt };
+        let (message, lparam) = keystroke(VK_2, true, false, alt_gr);
+        assert_eq!((message, lparam.context), (WM_KEYDOWN, false));
+
+        let (message, lparam) = keystroke(VK_F10, true, false, Modifiers::NONE);
+        assert_eq!((message, lparam.context), (WM_SYSKEYDOWN, false));
+        let (message, _) = keystroke(VK_1, false, false, Modifiers::NONE);
+        assert_eq!(message, WM_KEYUP);
+    }
+
+    #[test]
+    fn repeat_count_and_unknown_keys() {
+        let lparam = KeyLParam {
+            repeat_count: 3,
+            ..KeyLParam::key_down(VK_LBUTTON, true)
+        };
+
+        assert_eq!(lparam.scan_code, 0);
+        assert_eq!(lparam.bits(), 0x4000_0003);
+    }
+}
@@ -0,0 +1,378 @@
+//! Window placement maths, kept free of any platform call.
+
+use crate::geometry::Rect;
+
+/// A coordinate, either absolute or relative to the work area
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Length {
+    Pixels(i32),
+    Percent(f32),
+}
+
+impl Length {
+    fn resolve(self, total: i32) -> i32 {
+        match self {
+            Length::Pixels(pixels) => pixels,
+            Length::Percent(percent) => (total as f32 * percent / 100.0).round() as i32,
+        }
+    }
+}
+
+/// Where a single window goes in a `LayoutStrategy::Custom` layout
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Slot {
+    /// Offset from the left of the work area
+    pub x: Length,
+    /// Offset from the top of the work area
+    pub y: Length,
+    pub width: Length,
+    pub height: Length,
+    /// Monitor the slot is relative to, the main monitor when unset
+    #[serde(default)]
+    pub monitor: Option<usize>,
+}
+
+impl Slot {
+    pub fn resolve(&self, area: Rect) -> Rect {
+        Rect::new(
+            area.x + self.x.resolve(area.width),
+            area.y + self.y.resolve(area.height),
+            self.width.resolve(area.width),
+            self.height.resolve(area.height),
+        )
+    }
+}
+
+/// How the windows are arranged on the work area
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Default)]
+pub enum LayoutStrategy {
+    /// One big window on top, followers in a strip below
+    #[default]
+    MainBottom,
+    /// One big window on the left, followers in a column on the right
+    MainRight,
+    /// Every window gets the same cell, main window first
+    Grid,
+    /// Main window fills the work area, followers are thumbnails in the bottom-right corner
+    PictureInPicture,
+    /// Windows are stacked with a small offset, main window in front
+    Cascade,
+    /// User-defined slots, the main window takes the first one and followers
+    /// the next ones in order. Windows without a slot are left untouched.
+    Custom(Vec<Slot>),
+}
+
+/// Which monitors the layout uses, as indices in the monitor list (0 is the primary).
+/// Out of range indices fall back to the primary monitor.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LayoutMonitors {
+    /// Monitor the main window goes to
+    #[serde(default)]
+    pub main: usize,
+    /// When set to another monitor, the main window fills its monitor and the
+    /// followers are tiled on this one
+    #[serde(default)]
+    pub followers: Option<usize>,
+}
+
+/// Thumbnails are this many times smaller than the work area
+const PIP_RATIO: i32 = 5;
+/// Offset between two cascaded windows
+const CASCADE_STEP: i32 = 32;
+
+/// Returns one rect per window, in the same order as the windows.
+/// `None` means the window should not be moved.
+///
+/// `monitors` are the work areas, the primary one first.
+/// `main` is the index of the main window, it is clamped to the window count.
+pub fn layout(
+    strategy: &LayoutStrategy,
+    monitors: &[Rect],
+    placement: LayoutMonitors,
+    count: usize,
+    main: usize,
+) -> Vec<Option<Rect>> {
+    if count == 0 || monitors.is_empty() {
+        return vec![None; count];
+    }
+
+    let monitor = |index: usize| monitors.get(index).copied().unwrap_or(monitors[0]);
+    let area = monitor(placement.main);
+
+    let main = main.min(count - 1);
+    if let LayoutStrategy::Custom(slots) = strategy {
+        let mut rects = slots
+            .iter()
+            .map(|slot| Some(slot.resolve(slot.monitor.map_or(area, monitor))))
+            .chain(std::iter::repeat(None))
+            .take(count)
+            .collect::<Vec<_>>();
+
+        // Slot 0 belongs to the main window, followers keep their order
+        let main_rect = rects.remove(0);
+        rects.insert(main, main_rect);
+        return rects;
+    }
+
+    if count == 1 {
+        return vec![Some(area)];
+    }
+
+    let (main_rect, mut followers) = match placement.followers.map(monitor) {
+        Some(followers_area) if followers_area != area => {
+            (area, grid_cells(followers_area, count - 1))
+        }
+        _ => match strategy {
+            LayoutStrategy::MainBottom => main_bottom(area, count),
+            LayoutStrategy::MainRight => main_right(area, count),
+            LayoutStrategy::Grid => {
+                let mut cells = grid_cells(area, count);
+                (cells.remove(0), cells)
+            }
+            LayoutStrategy::PictureInPicture => picture_in_picture(area, count),
+            LayoutStrategy::Cascade => cascade(area, count),
+            LayoutStrategy::Custom(_) => unreachable!(),
+        },
+    };
+
+    followers.insert(main, main_rect);
+    followers.into_iter().map(Some).collect()
+}
+
+fn main_bottom(area: Rect, count: usize) -> (Rect, Vec<Rect>) {
+    let small_width = area.width / (count - 1) as i32;
+    let small_height = area.height / count as i32;
+
+    let (main, strip) = area.split_top(area.height - small_height);
+    let followers = (0..count as i32 - 1)
+        .map(|i| {
+            Rect::new(
+                strip.x + i * small_width,
+                strip.y,
+                small_width,
+                small_height,
+            )
+        })
+        .collect();
+
+    (main, followers)
+}
+
+fn main_right(area: Rect, count: usize) -> (Rect, Vec<Rect>) {
+    let small_width = area.width / count as i32;
+    let small_height = area.height / (count - 1) as i32;
+
+    let (main, column) = area.split_left(area.width - small_width);
+    let followers = (0..count as i32 - 1)
+        .map(|i| {
+            Rect::new(
+                column.x,
+                column.y + i * small_height,
+                small_width,
+                small_height,
+            )
+        })
+        .collect();
+
+    (main, followers)
+}
+
+/// Splits the area into `count` cells of the same size, row by row
+fn grid_cells(area: Rect, count: usize) -> Vec<Rect> {
+    let columns = (1..=count).find(|c| c * c >= count).unwrap_or(1);
+    let rows = count.div_ceil(columns).max(1);
+
+    let cell_width = area.width / columns as i32;
+    let cell_height = area.height / rows as i32;
+
+    (0..count)
+        .map(|i| {
+            let column = (i % columns) as i32;
+            let row = (i / columns) as i32;
+            Rect::new(
+                area.x + column * cell_width,
+                area.y + row * cell_height,
+                cell_width,
+                cell_height,
+            )
+        })
+        .collect()
+}
+
+fn picture_in_picture(area: Rect, count: usize) -> (Rect, Vec<Rect>) {
+    let small_width = area.width / PIP_RATIO;
+    let small_height = area.height / PIP_RATIO;
+    let per_row = PIP_RATIO as usize;
+
+    let followers = (0..count - 1)
+        .map(|i| {
+            let column = (i % per_row) as i32 + 1;
+            let row = (i / per_row) as i32 + 1;
+            Rect::new(
+                area.right() - column * small_width,
+                (area.bottom() - row * small_height).max(area.y),
+                small_width,
+                small_height,
+            )
+        })
+        .collect();
+
+    (area, followers)
+}
+
+fn cascade(area: Rect, count: usize) -> (Rect, Vec<Rect>) {
+    let steps = count as i32 - 1;
+    let step_x = CASCADE_STEP.min(area.width / 2 / steps);
+    let step_y = CASCADE_STEP.min(area.height / 2 / steps);
+
+    let width = area.width - steps * step_x;
+    let height = area.height - steps * step_y;
+
+    let mut windows = (0..count as i32)
+        .map(|i| Rect::new(area.x + i * step_x, area.y + i * step_y, width, height))
+        .collect::<Vec<_>>();
+
+    let main = windows.pop().unwrap();
+    (main, windows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const STRATEGIES: [LayoutStrategy; 5] = [
+        LayoutStrategy::MainBottom,
+        LayoutStrategy::MainRight,
+        LayoutStrategy::Grid,
+        LayoutStrategy::PictureInPicture,
+        LayoutStrategy::Cascade,
+    ];
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect::new(x, y, width, height)
+    }
+
+    fn area() -> impl Strategy<Value = Rect> {
+        (-2000..2000, -2000..2000, 200..4000, 200..3000).prop_map(|(x, y, w, h)| rect(x, y, w, h))
+    }
+
+    #[test]
+    fn main_bottom_matches_original_split() {
+        let rects = layout(
+            &LayoutStrategy::MainBottom,
+            &[rect(0, 0, 1920, 1080)],
+            LayoutMonitors::default(),
+            5,
+            0,
+        );
+
+        assert_eq!(rects[0], Some(rect(0, 0, 1920, 864)));
+        assert_eq!(rects[1], Some(rect(0, 864, 480, 216)));
+        assert_eq!(rects[4], Some(rect(1440, 864, 480, 216)));
+    }
+
+    #[test]
+    fn custom_slots_from_ron() {
+        let strategy: LayoutStrategy = ron::from_str(
+            "Custom([
+                (x: Percent(0), y: Percent(0), width: Percent(75), height: Percent(100)),
+                (x: Percent(75), y: Pixels(0), width: Percent(25), height: Pixels(300), monitor: Some(1)),
+                (x: Percent(75), y: Pixels(300), width: Percent(25), height: Pixels(300)),
+            ])",
+        )
+        .unwrap();
+
+        let monitors = [rect(0, 40, 3440, 1400), rect(3440, 0, 1080, 1880)];
+        let rects = layout(&strategy, &monitors, LayoutMonitors::default(), 4, 1);
+
+        // The main window takes slot 0, the others fill the next slots in order
+        assert_eq!(rects[1], Some(rect(0, 40, 2580, 1400)));
+        assert_eq!(rects[0], Some(rect(3440 + 810, 0, 270, 300)));
+        assert_eq!(rects[2], Some(rect(2580, 340, 860, 300)));
+        assert_eq!(rects[3], None);
+    }
+
+    #[test]
+    fn followers_on_their_own_monitor() {
+        let monitors = [rect(0, 0, 1920, 1040), rect(-1080, -400, 1080, 1880)];
+        let placement = LayoutMonitors {
+            main: 0,
+            followers: Some(1),
+        };
+        let rects = layout(&LayoutStrategy::MainBottom, &monitors, placement, 5, 2);
+
+        assert_eq!(rects[2], Some(monitors[0]));
+        assert_eq!(rects[0], Some(rect(-1080, -400, 540, 940)));
+        assert_eq!(rects[1], Some(rect(-540, -400, 540, 940)));
+        assert_eq!(rects[3], Some(rect(-1080, 540, 540, 940)));
+        assert_eq!(rects[4], Some(rect(-540, 540, 540, 940)));
+    }
+
+    #[test]
+    fn missing_monitor_falls_back_to_primary() {
+        let monitors = [rect(0, 0, 1920, 1040)];
+        let placement = LayoutMonitors {
+            main: 3,
+            followers: Some(3),
+        };
+        let split = layout(&LayoutStrategy::Grid, &monitors, placement, 4, 0);
+        let single = layout(
+            &LayoutStrategy::Grid,
+            &monitors,
+            LayoutMonitors::default(),
+            4,
+            0,
+        );
+
+        assert_eq!(split, single);
+    }
+
+    proptest! {
+        #[test]
+        fn one_rect_per_window(area in area(), count in 0usize..12, main in 0usize..12) {
+            for strategy in STRATEGIES {
+                prop_assert_eq!(layout(&strategy, &[area], LayoutMonitors::default(), count, main).len(), count);
+            }
+        }
+
+        #[test]
+        fn rects_stay_in_work_area(area in area(), count in 1usize..12, main in 0usize..12) {
+            for strategy in STRATEGIES {
+                for r in layout(&strategy, &[area], LayoutMonitors::default(), count, main).into_iter().flatten() {
+                    prop_assert!(area.contains(&r), "{:?} {:?} outside {:?}", strategy, r, area);
+                    prop_assert!(r.width > 0 && r.height > 0);
+                }
+            }
+        }
+
+        #[test]
+        fn tiles_do_not_overlap(area in area(), count in 1usize..12, main in 0usize..12) {
+            for strategy in [LayoutStrategy::MainBottom, LayoutStrategy::MainRight, LayoutStrategy::Grid] {
+                let rects = layout(&strategy, &[area], LayoutMonitors::default(), count, main).into_iter().flatten().collect::<Vec<_>>();
+                for (i, a) in rects.iter().enumerate() {
+                    for b in &rects[i + 1..] {
+                        prop_assert!(!a.intersects(b), "{:?} {:?} overlaps {:?}", strategy, a, b);
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn main_window_is_the_largest(area in area(), count in 2usize..12, main in 0usize..12) {
+            let strategies = [
+                LayoutStrategy::MainBottom,
+                LayoutStrategy::MainRight,
+                LayoutStrategy::PictureInPicture,
+                LayoutStrategy::Cascade,
+            ];
+            let main = main % count;
+            for strategy in strategies {
+                let rects = layout(&strategy, &[area], LayoutMonitors::default(), count, main).into_iter().flatten().collect::<Vec<_>>();
+                for (i, r) in rects.iter().enumerate() {
+                    prop_assert!(i == main || r.area() <= rects[main].area(), "{:?}", strategy);
+                }
+            }
+        }
+    }
+}
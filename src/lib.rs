@@ -1,129 +1,510 @@
-use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Instant;
 
 use windows::{
-    messaging::{
-        HWND_NOTOPMOST, HWND_TOPMOST, KF_REPEAT, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP,
-    },
-    process::EnumWindows,
+    messaging::{HWND_NOTOPMOST, HWND_TOPMOST},
     styles::{
-        GWL_EXSTYLE, GWL_STYLE, WS_CAPTION, WS_EX_CLIENTEDGE, WS_EX_DLGMODALFRAME,
-        WS_EX_STATICEDGE, WS_MAXIMIZEBOX, WS_MINIMIZEBOX, WS_SYSMENU, WS_THICKFRAME,
-    },
-    vk::{self, GetAsyncKeyState, MK_LBUTTON},
-    windowing::{
-        GetClientRect, GetForegroundWindow, GetWindowLong, GetWindowRect, GetWindowText,
-        GetWindowTextLength, MoveWindow, PostMessage, SetForegroundWindow, SetWindowLong,
-        SetWindowPos, SystemParametersInfo, SPI_GETWORKAREA, SWP_FRAMECHANGED, SWP_NOMOVE,
-        SWP_NOOWNERZORDER, SWP_NOSIZE, SWP_NOZORDER, SWP_SHOWWINDOW,
-        SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+        WS_CAPTION, WS_EX_CLIENTEDGE, WS_EX_DLGMODALFRAME, WS_EX_STATICEDGE, WS_MAXIMIZEBOX,
+        WS_MINIMIZEBOX, WS_SYSMENU, WS_THICKFRAME,
     },
-    BOOL, HWND, LPARAM, RECT,
+    HWND,
 };
 
 pub use windows::WPARAM;
 
+pub mod chat;
+pub mod chord;
 mod config;
-use crate::config::Key;
-pub use crate::config::{BotAction, Config, LayoutOptions, Mode};
+pub mod delay;
+pub mod dispatch;
+mod error;
+mod events;
+pub mod geometry;
+pub mod groups;
+pub mod hotkeys;
+pub mod input;
+pub mod keystroke;
+pub mod layout;
+pub mod matcher;
+pub mod mouse;
+pub mod platform;
+pub mod profiles;
+pub mod repeat;
+pub mod restore;
+pub mod sequence;
+pub mod slots;
+use crate::chat::ChatMode;
+use crate::chord::{Chord, Framing, Modifiers};
+pub use crate::config::{BotAction, Config, Key, LayoutOptions, Mode, WaitOptions};
+use crate::delay::Delays;
+pub use crate::delay::{Delay, DelayOptions};
+pub use crate::dispatch::DispatchStats;
+use crate::dispatch::{Dispatcher, Job};
+pub use crate::error::Error;
+pub use crate::events::WindowEvent;
+pub use crate::geometry::Rect;
+use crate::groups::Member;
+pub use crate::groups::Selector;
+use crate::hotkeys::Hotkeys;
+pub use crate::input::{InputMode, InputSource, KeyEvent, SyntheticInput};
+use crate::keystroke::KeyLParam;
+pub use crate::layout::{LayoutMonitors, LayoutStrategy};
+pub use crate::matcher::{MatcherSpec, WindowName};
+use crate::matcher::{WindowInfo, WindowMatcher};
+pub use crate::mouse::{MouseButton, MouseEvent, MouseMapping, MouseOptions};
+pub use crate::platform::{FakePlatform, NativePlatform, Platform};
+use crate::profiles::KeyMap;
+pub use crate::profiles::Profile;
+pub use crate::repeat::{Repeat, RepeatOptions};
+use crate::restore::Placement;
+use crate::sequence::Stroke;
+pub use crate::sequence::{Remap, Step};
+use crate::slots::{SlotCandidate, SlotRecord};
+pub use crate::slots::{SlotOptions, SlotOrder, SlotRule};
 
 #[derive(Debug, Clone)]
 struct Window {
     hwnd: HWND,
     title: String,
-    rect: RECT,
+    pid: u32,
+    rect: Rect,
+    /// Stable position of the client, `windows` is sorted by it
+    slot: usize,
 }
 
-pub struct App {
+pub struct App<P: Platform> {
+    platform: P,
+    /// Compiled `config.window_name`
+    matcher: WindowMatcher,
     main_hwnd: Option<HWND>,
     windows: Vec<Window>,
-
-    keyboard: BTreeSet<usize>,
+    /// How every window looked before we first touched it
+    originals: Vec<Placement>,
+    /// Windows we moved or restyled, the only ones put back on exit
+    touched: Vec<HWND>,
+    /// Slots held by clients in this run and the previous ones
+    remembered_slots: Vec<SlotRecord>,
+    last_refresh: Instant,
+    listeners: Vec<Sender<WindowEvent>>,
+    /// Group receiving the keys without a group of their own, every follower when `None`
+    active_group: Option<String>,
+    hotkeys: Hotkeys,
+    /// Where `config` was read from, for `Shortcut::ReloadConfig`
+    config_path: Option<PathBuf>,
+    /// Set by `Shortcut::Quit`
+    quit: bool,
+
+    input: Box<dyn InputSource<P>>,
+    /// Modifier keys held on the leader, as the input events tell
+    leader_modifiers: Vec<Key>,
+    /// Leader keys being held, and what each follower was sent for them
+    keyboard: HashMap<Key, Vec<Sent>>,
+    /// Leader buttons being held, and the followers their press was sent to
+    mouse_buttons: HashMap<MouseButton, Vec<HWND>>,
+    /// Window the leader's input was last read from
+    leader: Option<HWND>,
+    /// Keys sent down to a window and not up yet, in the order they went down
+    keys_down: Vec<(HWND, Key)>,
+    /// Presses are kept from the followers, releases still go through
+    paused: bool,
+    /// Whether `config.pause_key` is down, it toggles the pause once per press
+    pause_held: bool,
+    chat: ChatMode,
+    dispatcher: Dispatcher,
     pub config: Config,
 }
 
-impl App {
-    pub fn new(config: Config) -> Self {
-        let window_name = config.window_name.clone();
+/// What a follower was sent for a leader key that is still held
+#[derive(Debug, Clone)]
+struct Sent {
+    hwnd: HWND,
+    chord: Chord,
+    /// Modifiers changed on the follower until the key is released
+    framing: Framing,
+    /// Policy of the follower for the held key
+    repeat: Repeat,
+    pressed: Instant,
+    /// Repeats sent so far
+    repeats: u32,
+}
+
+/// Keystrokes for one window, which holds the `held` modifier keys before the first one
+#[derive(Debug, Clone)]
+struct Timeline {
+    hwnd: HWND,
+    held: Vec<Key>,
+    strokes: Vec<Stroke>,
+}
+
+/// Keystrokes sent right away
+fn strokes(keys: Vec<(Key, bool)>) -> Vec<Stroke> {
+    keys.into_iter()
+        .map(|(key, down)| Stroke::now(key, down))
+        .collect()
+}
+
+impl<P: Platform> App<P> {
+    /// Finds the windows to control, waiting for them if the config asks to
+    pub fn new(platform: P, config: Config) -> Result<Self, Error> {
+        Self::new_until(platform, config, &|| false)
+    }
+
+    /// Same as `new`, but stops waiting for the windows once `quit` returns true
+    pub fn new_until(platform: P, config: Config, quit: &dyn Fn() -> bool) -> Result<Self, Error> {
+        if config.window_name.is_empty() {
+            return Err(Error::EmptyWindowName);
+        }
+
+        let matcher = WindowMatcher::new(&config.window_name)
+            .map_err(|e| Error::InvalidWindowName(e.to_string()))?;
+        let remembered_slots = config
+            .slots
+            .file
+            .as_ref()
+            .and_then(|path| slots::load(path).ok())
+            .unwrap_or_default();
+        let wait = config.wait.clone();
+        let active_group = config.active_group.clone();
+        let dispatcher = Dispatcher::new(platform.clone(), Delays::new(config.delays.clone()));
+        let input = input::source(&platform, config.input);
         let mut app = Self {
+            platform,
+            matcher,
             windows: Vec::new(),
             main_hwnd: None,
-            keyboard: BTreeSet::new(),
+            originals: Vec::new(),
+            touched: Vec::new(),
+            remembered_slots,
+            last_refresh: Instant::now(),
+            listeners: Vec::new(),
+            active_group,
+            hotkeys: Hotkeys::new(),
+            config_path: None,
+            quit: false,
+            input,
+            leader_modifiers: Vec::new(),
+            keyboard: HashMap::new(),
+            mouse_buttons: HashMap::new(),
+            leader: None,
+            keys_down: Vec::new(),
+            paused: false,
+            pause_held: false,
+            chat: ChatMode::default(),
+            dispatcher,
             config,
         };
 
-        app.update_windows(window_name);
-        app
+        match wait {
+            Some(options) => app.wait_for_windows(&options, quit)?,
+            None => app.update_windows()?,
+        }
+        Ok(app)
+    }
+
+    pub fn platform(&self) -> &P {
+        &self.platform
     }
 
     pub fn is_main_focus(&self) -> bool {
-        unsafe { Some(GetForegroundWindow()) == self.main_hwnd }
+        Some(self.platform.foreground_window()) == self.main_hwnd
     }
 
     pub fn has_hwnd(&self, hwnd: HWND) -> bool {
         self.windows.iter().any(|w| w.hwnd == hwnd)
     }
 
-    pub fn update_windows<P: Into<String>>(&mut self, pattern: P) {
-        struct HWNDCollector(Vec<Window>, String);
+    fn matching_windows(&self) -> Vec<Window> {
+        self.platform
+            .windows()
+            .into_iter()
+            .map(|hwnd| WindowInfo::query(&self.platform, hwnd))
+            .filter(|info| self.matcher.matches(info))
+            .map(|info| Window {
+                hwnd: info.hwnd,
+                title: info.title,
+                pid: info.pid,
+                rect: self.platform.window_rect(info.hwnd),
+                slot: 0,
+            })
+            .collect()
+    }
+
+    /// Gives the new windows a slot that no window in `self.windows` holds, and remembers it
+    fn assign_slots(&mut self, new: &mut [Window]) {
+        let candidates = new
+            .iter()
+            .map(|window| SlotCandidate {
+                title: window.title.clone(),
+                pid: window.pid,
+                started: self.platform.process_start_time(window.pid),
+            })
+            .collect::<Vec<_>>();
+        let taken = self.windows.iter().map(|w| w.slot).collect::<Vec<_>>();
+
+        let assigned = slots::assign_slots(
+            &self.config.slots,
+            &self.remembered_slots,
+            &taken,
+            &candidates,
+        );
+        for (window, slot) in new.iter_mut().zip(assigned) {
+            window.slot = slot;
+        }
+
+        let before = self.remembered_slots.clone();
+        let current = new
+            .iter()
+            .zip(&candidates)
+            .map(|(window, candidate)| SlotRecord {
+                slot: window.slot,
+                title: window.title.clone(),
+                pid: window.pid,
+                started: candidate.started,
+            })
+            .collect();
+        slots::remember(&mut self.remembered_slots, current);
+
+        if let (true, Some(path)) = (before != self.remembered_slots, &self.config.slots.file) {
+            if let Err(e) = slots::save(path, &self.remembered_slots) {
+                eprintln!("Could not save slots to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Takes control of every window matching `window_name`, the lowest slot is the main
+    pub fn update_windows(&mut self) -> Result<(), Error> {
+        let mut windows = self.matching_windows();
+        self.windows.clear();
+        self.assign_slots(&mut windows);
+        windows.sort_by_key(|w| w.slot);
+        self.windows = windows;
+
+        let Some(main) = self.windows.first() else {
+            self.main_hwnd = None;
+            return Err(Error::NoMatchingWindows {
+                pattern: self.config.window_name.to_string(),
+            });
+        };
+
+        self.main_hwnd = Some(main.hwnd);
+        self.save_originals();
+        Ok(())
+    }
 
-        unsafe extern "system" fn callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
-            let name_length = GetWindowTextLength(hwnd);
-            let mut buffer = vec![0u8; name_length as usize + 1];
-            GetWindowText(hwnd, &mut buffer);
+    /// Receives every `WindowEvent` from now on
+    pub fn subscribe(&mut self) -> Receiver<WindowEvent> {
+        let (sender, receiver) = channel();
+        self.listeners.push(sender);
+        receiver
+    }
 
-            let hwnds_collector = &mut *(lparam.0 as *mut HWNDCollector);
+    /// Calls `refresh_windows` once every `refresh_interval`
+    pub fn poll_windows(&mut self) -> Vec<WindowEvent> {
+        match self.config.refresh_interval {
+            Some(interval) if self.last_refresh.elapsed() >= interval.into() => {
+                self.refresh_windows()
+            }
+            _ => Vec::new(),
+        }
+    }
 
-            let name = String::from_utf8_lossy(&buffer);
-            if name.to_lowercase().contains(&hwnds_collector.1) {
-                hwnds_collector.0.push(Window {
-                    hwnd,
-                    title: name.to_string(),
-                    rect: get_rect(hwnd),
+    /// Looks for windows that were closed or opened since the last time.
+    ///
+    /// Dead windows are dropped and free their slot, new matching windows get
+    /// a slot of their own, and the layout is applied again when `LayoutOptions::Always` is set.
+    pub fn refresh_windows(&mut self) -> Vec<WindowEvent> {
+        self.last_refresh = Instant::now();
+
+        let mut matching = self.matching_windows();
+
+        let mut events = Vec::new();
+        let platform = &self.platform;
+        self.windows.retain(|window| {
+            let alive =
+                platform.is_window(window.hwnd) && matching.iter().any(|w| w.hwnd == window.hwnd);
+            if !alive {
+                events.push(WindowEvent::Removed {
+                    hwnd: window.hwnd,
+                    title: window.title.clone(),
                 });
             }
+            alive
+        });
 
-            // Return true to continue enumeration
-            BOOL::from(true)
+        matching.retain(|window| !self.windows.iter().any(|w| w.hwnd == window.hwnd));
+        self.assign_slots(&mut matching);
+        for window in matching {
+            events.push(WindowEvent::Added {
+                hwnd: window.hwnd,
+                title: window.title.clone(),
+            });
+            self.windows.push(window);
         }
+        self.windows.sort_by_key(|w| w.slot);
 
-        let pattern = pattern.into().to_lowercase();
-        let mut collector = HWNDCollector(Vec::new(), pattern);
+        if !self.main_hwnd.is_some_and(|hwnd| self.has_hwnd(hwnd)) {
+            let main_hwnd = self.windows.first().map(|w| w.hwnd);
+            if main_hwnd != self.main_hwnd {
+                self.main_hwnd = main_hwnd;
+                events.push(WindowEvent::MainChanged { hwnd: main_hwnd });
+            }
+        }
 
-        unsafe {
-            // SAFETY: we are passing Collector as a pointer to the callback,
-            // which will be used to store the HWNDs. No other thread is using it.
-            EnumWindows(Some(callback), LPARAM(&mut collector as *mut _ as isize));
-        };
+        if !events.is_empty() {
+            self.save_originals();
+
+            if matches!(self.config.layout, LayoutOptions::Always) && self.windows.len() > 1 {
+                self.layout_windows();
+                events.push(WindowEvent::LaidOut);
+            }
+        }
+
+        self.listeners
+            .retain(|listener| events.iter().all(|e| listener.send(e.clone()).is_ok()));
+        events
+    }
+
+    /// Polls until at least `options.count` windows match `window_name`, or `quit` returns true
+    pub fn wait_for_windows(
+        &mut self,
+        options: &WaitOptions,
+        quit: &dyn Fn() -> bool,
+    ) -> Result<(), Error> {
+        let start = std::time::Instant::now();
+        let timeout = options.timeout.map(std::time::Duration::from);
+        let wanted = options.count.max(1);
+
+        loop {
+            let _ = self.update_windows();
+
+            let found = self.windows.len();
+            if found >= wanted {
+                return Ok(());
+            }
+
+            if quit() {
+                return Err(Error::Interrupted);
+            }
+
+            if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+                return Err(Error::Timeout {
+                    pattern: self.config.window_name.to_string(),
+                    found,
+                    wanted,
+                });
+            }
+
+            std::thread::sleep(options.poll.into());
+        }
+    }
+
+    /// Records the placement of windows seen for the first time
+    fn save_originals(&mut self) {
+        for window in &self.windows {
+            let hwnd = window.hwnd.0;
+            if !self.originals.iter().any(|p| p.hwnd == hwnd) {
+                self.originals
+                    .push(Placement::capture(&self.platform, window.hwnd));
+            }
+        }
+    }
+
+    /// Marks `hwnds` as changed by us, before they are, and persists their
+    /// original placement for `restore` after a crash
+    fn touch(&mut self, hwnds: &[HWND]) {
+        let mut changed = false;
+        for hwnd in hwnds {
+            if !self.touched.contains(hwnd) {
+                self.touched.push(*hwnd);
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.save_touched();
+        }
+    }
+
+    fn save_touched(&self) {
+        let touched = self
+            .originals
+            .iter()
+            .filter(|p| self.touched.contains(&HWND(p.hwnd)))
+            .copied()
+            .collect::<Vec<_>>();
+
+        if let Some(path) = &self.config.state_file {
+            if let Err(e) = restore::save(path, &touched) {
+                eprintln!(
+                    "Could not save window placements to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Puts every window we moved or restyled back in its original place, with
+    /// its borders. The others were left as they were, maybe placed by hand.
+    pub fn restore_windows(&mut self) {
+        let touched = self
+            .originals
+            .iter()
+            .filter(|p| self.touched.contains(&HWND(p.hwnd)))
+            .filter(|p| p.is_current(&self.platform));
+        for placement in touched {
+            placement.restore(&self.platform);
+        }
+        self.touched.clear();
+        self.save_touched();
 
-        self.windows = collector.0;
-        self.main_hwnd = Some(self.windows[0].hwnd)
+        for window in self.windows.iter_mut() {
+            window.rect = self.platform.window_rect(window.hwnd);
+        }
+    }
+
+    /// Restores the windows and forgets the saved placements, nothing is left to recover
+    pub fn shutdown(&mut self) {
+        self.release_all();
+        self.restore_windows();
+
+        if let Some(path) = &self.config.state_file {
+            let _ = std::fs::remove_file(path);
+        }
     }
 
     /// Returns true if the main loop needs to be restarted
     pub fn global_shortcuts(&mut self) -> bool {
-        use config::Shortcut;
+        self.shortcuts_at(Instant::now())
+    }
 
-        // If the user is not pressing the shortcut keys, ignore (LShift + LAlt)
-        if unsafe { GetAsyncKeyState(i32::from(vk::VK_LSHIFT.0)) } as u16 & 0x8000 == 0
-            || unsafe { GetAsyncKeyState(i32::from(vk::VK_LMENU.0)) } as u16 & 0x8000 == 0
-        {
-            return false;
-        }
+    /// `global_shortcuts` with the keys polled `now`
+    fn shortcuts_at(&mut self, now: Instant) -> bool {
+        use config::Shortcut;
 
+        let prefix_held = self.shortcut_prefix_held();
         let shortcuts = &self.config.shortcuts.clone();
-        let shortcuts_keys = shortcuts.keys().cloned().collect::<Vec<_>>();
+        let down = shortcuts
+            .keys()
+            .copied()
+            .filter(|key| self.platform.is_key_down(*key))
+            .collect::<Vec<_>>();
+        let pressed = self.hotkeys.pressed(
+            now,
+            &down,
+            prefix_held,
+            self.config.shortcut_debounce.into(),
+        );
 
         let mut update = false;
-        for key in shortcuts_keys {
-            if unsafe { GetAsyncKeyState(key as i32) as u32 } & 0x8000 == 0 {
-                continue;
-            }
-
+        for key in pressed {
             let action = shortcuts.get(&key).unwrap();
             match action {
                 Shortcut::Foreground => self.foreground(),
                 Shortcut::Layout => {
-                    let foreground_hwnd = unsafe { GetForegroundWindow() };
+                    let foreground_hwnd = self.platform.foreground_window();
                     if self.has_hwnd(foreground_hwnd) {
                         self.main_hwnd = Some(foreground_hwnd);
                     }
@@ -131,17 +512,153 @@ impl App {
                     self.layout_windows()
                 }
                 Shortcut::Mode(mode) => {
+                    // The other modes never send the ups of what mimic pressed
+                    self.release_all();
                     self.config.mode = mode.clone();
                     update = true;
                 }
+                Shortcut::Restore => self.restore_windows(),
+                Shortcut::Group(name) => self.active_group = Some(name.clone()),
+                Shortcut::AllFollowers => self.active_group = None,
+                Shortcut::ReleaseAll => self.release_all(),
+                Shortcut::NextLeader => self.cycle_leader(1),
+                Shortcut::PreviousLeader => self.cycle_leader(-1),
+                Shortcut::FocusSlot(slot) => {
+                    let window = self.windows.iter().find(|w| w.slot == *slot);
+                    if let Some(hwnd) = window.map(|w| w.hwnd) {
+                        self.focus_leader(hwnd);
+                    }
+                }
+                Shortcut::ToggleBorders => self.toggle_borders(),
+                Shortcut::ReloadConfig => {
+                    if let Err(e) = self.reload_config() {
+                        eprintln!("Could not reload the config: {}", e);
+                    }
+                    // The mode may have changed
+                    update = true;
+                }
+                Shortcut::Pause => self.paused = !self.paused,
+                Shortcut::Quit => {
+                    self.quit = true;
+                    update = true;
+                }
             }
         }
 
         update
     }
 
+    /// Whether every key of `config.shortcut_prefix` is down
+    fn shortcut_prefix_held(&self) -> bool {
+        self.config
+            .shortcut_prefix
+            .iter()
+            .all(|key| self.platform.is_key_down(*key))
+    }
+
+    /// Lets `Shortcut::ReloadConfig` read the config again from `path`
+    pub fn set_config_path<T: Into<PathBuf>>(&mut self, path: T) {
+        self.config_path = Some(path.into());
+    }
+
+    /// Whether `Shortcut::Quit` was pressed, the loops return and the app should shut down
+    pub fn quit_requested(&self) -> bool {
+        self.quit
+    }
+
+    /// Reads the config file again and applies it: the window matcher, the
+    /// delays, the input source and the active group. Held keys are released
+    /// first, they may not be bound anymore.
+    ///
+    /// The current config is kept when the file is invalid.
+    pub fn reload_config(&mut self) -> Result<(), Error> {
+        let Some(path) = &self.config_path else {
+            return Ok(());
+        };
+
+        let config = Config::try_load(path)?;
+        if config.window_name.is_empty() {
+            return Err(Error::EmptyWindowName);
+        }
+        let matcher = WindowMatcher::new(&config.window_name)
+            .map_err(|e| Error::InvalidWindowName(e.to_string()))?;
+
+        self.release_all();
+        self.matcher = matcher;
+        // Nothing is left to post, `release_all` cancelled what was queued
+        self.dispatcher =
+            Dispatcher::new(self.platform.clone(), Delays::new(config.delays.clone()));
+        if config.input != self.config.input {
+            // There is one hook at a time, the old one goes first
+            self.input = Box::new(input::Poller::default());
+            self.input = input::source(&self.platform, config.input);
+        }
+        self.active_group = config.active_group.clone();
+        self.config = config;
+
+        self.refresh_windows();
+        Ok(())
+    }
+
+    /// Makes `hwnd` the leader, swapping it with the current one when the
+    /// layout is always applied
+    fn focus_leader(&mut self, hwnd: HWND) {
+        self.platform.set_foreground_window(hwnd);
+
+        let swapped = matches!(self.config.layout, LayoutOptions::Always) && self.swap_windows();
+        if !swapped {
+            self.main_hwnd = Some(hwnd);
+        }
+    }
+
+    /// Makes the window `step` slots after the leader the leader, wrapping around
+    fn cycle_leader(&mut self, step: isize) {
+        if self.windows.is_empty() {
+            return;
+        }
+
+        let foreground = self.platform.foreground_window();
+        let leader = if self.has_hwnd(foreground) {
+            Some(foreground)
+        } else {
+            self.main_hwnd
+        };
+        let current = self
+            .windows
+            .iter()
+            .position(|w| Some(w.hwnd) == leader)
+            .unwrap_or(0);
+
+        let next = (current as isize + step).rem_euclid(self.windows.len() as isize);
+        self.focus_leader(self.windows[next as usize].hwnd);
+    }
+
+    /// Shows the borders of every follower if the first one has none, hides them otherwise
+    fn toggle_borders(&mut self) {
+        let followers = self
+            .windows
+            .iter()
+            .map(|w| w.hwnd)
+            .filter(|hwnd| Some(*hwnd) != self.main_hwnd)
+            .collect::<Vec<_>>();
+        let Some(first) = followers.first() else {
+            return;
+        };
+
+        let borders = !has_borders(&self.platform, *first);
+        self.touch(&followers);
+        for hwnd in followers {
+            set_borders(&self.platform, hwnd, borders);
+        }
+    }
+
+    /// Reads the leader's keys from `input` from now on
+    pub fn set_input<I: InputSource<P> + 'static>(&mut self, input: I) {
+        self.input = Box::new(input);
+    }
+
     pub fn get_foreground_window(&mut self) -> bool {
-        let hwnd = unsafe { GetForegroundWindow() };
+        let hwnd = self.platform.foreground_window();
         if Some(hwnd) == self.main_hwnd {
             return false;
         }
@@ -149,87 +666,380 @@ impl App {
         if self.has_hwnd(hwnd) {
             self.main_hwnd = Some(hwnd);
         }
-        return true;
+        true
     }
 
     pub fn mimic(&mut self) {
-        enum KeyState {
-            Insert,
-            Remove,
-            None,
-        }
-
-        fn mimic_key(app: &App, key: Key, remaped_key: Key, other_hwnds: &[Window]) -> KeyState {
-            let state = unsafe { GetAsyncKeyState(key as i32) } as u16;
-            let key_already_pressed = app.keyboard.contains(&(key as usize));
-            if state & 0x8000 != 0 {
-                app.send_key_hwnds(WM_KEYDOWN, WPARAM(remaped_key as usize), &other_hwnds);
-                if key_already_pressed {
-                    KeyState::None
-                } else {
-                    KeyState::Insert
-                }
-            } else if key_already_pressed {
-                app.send_key_hwnds(WM_KEYUP, WPARAM(remaped_key as usize), &other_hwnds);
-                KeyState::Remove
-            } else {
-                KeyState::None
+        self.mimic_at(Instant::now());
+    }
+
+    /// `mimic` with the input read `now`
+    fn mimic_at(&mut self, now: Instant) {
+        let main_hwnd = self.platform.foreground_window();
+
+        let followers = self
+            .windows
+            .iter()
+            .filter(|window| window.hwnd != main_hwnd)
+            .map(|window| {
+                let keys = profiles::key_map(&self.config, &window.title, window.slot);
+                (window.clone(), keys)
+            })
+            .collect::<Vec<_>>();
+
+        // Held keys stay watched, even if no binding would send them anymore.
+        // Modifiers come first, so a chord pressed at once sees them.
+        let mut watched = Modifiers::KEYS.to_vec();
+        watched.extend(self.config.pause_key);
+        let held = self.keyboard.keys().copied();
+        let bound = followers
+            .iter()
+            .flat_map(|(_, keys)| keys.keys())
+            .map(|chord| chord.key);
+        for key in held.chain(bound) {
+            if !watched.contains(&key) {
+                watched.push(key);
             }
         }
 
-        let main_hwnd = unsafe { GetForegroundWindow() };
+        let events = self.input.poll(&self.platform, &watched);
+        let mouse = self.input.poll_mouse(&self.platform);
+        // The releases of what was held will be typed elsewhere, or on a former follower
+        if self.leader != Some(main_hwnd) {
+            self.release_all();
+            self.leader = Some(main_hwnd);
+        }
+        // Keys typed in other windows are not for the followers
         if !self.has_hwnd(main_hwnd) {
             return;
         }
 
-        let other_hwnds = self
-            .windows
+        // Behind the prefix, the prefix and the shortcut keys are for us
+        let shortcut = self.shortcut_prefix_held();
+        for event in events {
+            self.track_modifiers(event);
+            if Some(event.key) == self.config.pause_key {
+                if event.down && !self.pause_held {
+                    self.paused = !self.paused;
+                }
+                self.pause_held = event.down;
+                continue;
+            }
+
+            let chatting = self.config.chat_mode && !self.chat.filter(event);
+            let for_us = shortcut
+                && (self.config.shortcut_prefix.contains(&event.key)
+                    || self.config.shortcuts.contains_key(&event.key));
+            if event.down && (self.paused || chatting || for_us) {
+                continue;
+            }
+            self.broadcast(&followers, event, now);
+        }
+        self.repeat_held(now);
+        if self.config.mouse.enabled {
+            let leader = self.platform.client_rect(main_hwnd);
+            for event in mouse {
+                self.broadcast_mouse(&followers, leader, event);
+            }
+        }
+    }
+
+    /// Lifts every key and button the windows were sent down and not up, and
+    /// forgets what the leader holds: a key still held is pressed again. The
+    /// input not read yet is dropped, it was typed for what was released, and
+    /// so are the keystrokes queued and not due yet, but for their releases.
+    ///
+    /// Waits for the releases to be posted, the process may be about to end.
+    pub fn release_all(&mut self) {
+        self.dispatcher.cancel();
+        let mut timelines: Vec<Timeline> = Vec::new();
+        for (hwnd, key) in self.keys_down.iter().rev() {
+            let stroke = Stroke::now(*key, false);
+            match timelines.iter_mut().find(|timeline| timeline.hwnd == *hwnd) {
+                Some(timeline) => timeline.strokes.push(stroke),
+                None => timelines.push(Timeline {
+                    hwnd: *hwnd,
+                    held: self.window_keys(*hwnd),
+                    strokes: vec![stroke],
+                }),
+            }
+        }
+        self.send_key_hwnds(timelines);
+
+        let leader = self
+            .leader
+            .map(|hwnd| self.platform.client_rect(hwnd))
+            .unwrap_or_default();
+        let (x, y) = self.platform.cursor_position();
+        for button in MouseButton::ALL {
+            if let Some(hwnds) = self.mouse_buttons.remove(&button) {
+                let event = MouseEvent::Button {
+                    button,
+                    down: false,
+                    x,
+                    y,
+                };
+                self.send_mouse_hwnds(event, leader, &hwnds);
+            }
+        }
+
+        self.input.discard(&self.platform);
+        self.keyboard.clear();
+        self.leader_modifiers.clear();
+        self.flush();
+    }
+
+    /// Keys sent down to `hwnd` and not up yet
+    fn window_keys(&self, hwnd: HWND) -> Vec<Key> {
+        self.keys_down
             .iter()
-            .cloned()
-            .filter(|window| window.hwnd != main_hwnd)
-            .collect::<Vec<_>>();
+            .filter(|(down, _)| *down == hwnd)
+            .map(|(_, key)| *key)
+            .collect()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
 
-        let skip_keybind = &self.config.skip_keybind;
-        let remap_keybind = &self.config.remap_keybind;
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
 
-        for key in &self.config.keybind {
-            if skip_keybind.contains(key) || remap_keybind.contains_key(key) {
-                continue;
+    /// Whether the leader is typing in chat, with `config.chat_mode` on
+    pub fn is_chatting(&self) -> bool {
+        self.config.chat_mode && self.chat.is_typing()
+    }
+
+    /// Follows the modifiers held on the leader, broadcast or not
+    fn track_modifiers(&mut self, event: KeyEvent) {
+        if Modifiers::of(event.key) != Modifiers::NONE {
+            self.leader_modifiers.retain(|held| *held != event.key);
+            if event.down {
+                self.leader_modifiers.push(event.key);
             }
+        }
+    }
 
-            match mimic_key(self, *key, *key, &other_hwnds) {
-                KeyState::Insert => self.keyboard.insert(*key as usize),
-                KeyState::Remove => self.keyboard.remove(&(*key as usize)),
-                KeyState::None => false,
-            };
+    /// Modifiers held on the leader
+    fn leader_held(&self) -> Modifiers {
+        self.leader_modifiers
+            .iter()
+            .fold(Modifiers::NONE, |held, key| held.union(Modifiers::of(*key)))
+    }
+
+    /// Sends every follower what `event`, read `now`, means for it
+    fn broadcast(&mut self, followers: &[(Window, KeyMap)], event: KeyEvent, now: Instant) {
+        let KeyEvent { key, down } = event;
+        let held = self.leader_held();
+
+        match (down, self.keyboard.remove(&key)) {
+            // Autorepeat, the followers keep getting what they got on the first press
+            (true, Some(mut sent)) => {
+                let timelines = sent
+                    .iter()
+                    .filter(|sent| sent.repeat == Repeat::Passthrough)
+                    .filter_map(|sent| self.repeat_timeline(sent, 1))
+                    .collect();
+                self.send_key_hwnds(timelines);
+
+                for sent in sent.iter_mut() {
+                    if sent.repeat == Repeat::Passthrough {
+                        sent.repeats += 1;
+                    }
+                }
+                self.keyboard.insert(key, sent);
+            }
+            (true, None) => {
+                let members = followers
+                    .iter()
+                    .map(|(window, _)| Member {
+                        title: &window.title,
+                        slot: window.slot,
+                    })
+                    .collect::<Vec<_>>();
+                let recipients =
+                    groups::recipients(&self.config, self.active_group.as_deref(), key, &members);
+
+                let bindings = followers
+                    .iter()
+                    .zip(recipients)
+                    .filter(|(_, recipient)| *recipient)
+                    .filter_map(|((window, keys), _)| {
+                        chord::lookup(keys, held, key)
+                            .map(|(from, to)| (window.hwnd, *from, to.clone()))
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut sent = Vec::new();
+                let mut timelines = Vec::new();
+                for (hwnd, from, to) in bindings {
+                    let held = self.follower_keys(hwnd);
+                    let to = match to {
+                        Remap::Chord(to) => to,
+                        // Typed once, there is nothing to repeat or release
+                        Remap::Sequence(steps) => {
+                            let strokes = sequence::expand(&steps, &held);
+                            timelines.push(Timeline {
+                                hwnd,
+                                held,
+                                strokes,
+                            });
+                            continue;
+                        }
+                    };
+
+                    // A bare key keeps whatever modifiers the follower has, a chord gets
+                    // exactly its own
+                    let framing =
+                        if from.modifiers == Modifiers::NONE && to.modifiers == Modifiers::NONE {
+                            Framing::default()
+                        } else {
+                            Framing::around(&held, to)
+                        };
+
+                    timelines.push(Timeline {
+                        hwnd,
+                        held,
+                        strokes: strokes(framing.press(to.key)),
+                    });
+                    sent.push(Sent {
+                        hwnd,
+                        chord: to,
+                        framing,
+                        repeat: self.config.repeat.policy(self.slot(hwnd)),
+                        pressed: now,
+                        repeats: 0,
+                    });
+                }
+                self.send_key_hwnds(timelines);
+                self.keyboard.insert(key, sent);
+            }
+            (false, Some(sent)) => {
+                let timelines = sent
+                    .iter()
+                    .map(|sent| {
+                        let still_held = self.follower_keys(sent.hwnd);
+                        let release = sent.framing.release(sent.chord.key, &still_held);
+                        Timeline {
+                            hwnd: sent.hwnd,
+                            held: sent.framing.inside(&still_held),
+                            strokes: strokes(release),
+                        }
+                    })
+                    .collect();
+                self.send_key_hwnds(timelines);
+            }
+            (false, None) => {}
         }
+    }
 
-        for (key, remaped_key) in remap_keybind {
-            match mimic_key(self, *key, *remaped_key, &other_hwnds) {
-                KeyState::Insert => self.keyboard.insert(*key as usize),
-                KeyState::Remove => self.keyboard.remove(&(*key as usize)),
-                KeyState::None => false,
-            };
+    /// Mirrors on the followers what the leader did with the mouse over its
+    /// `leader` client area.
+    ///
+    /// Presses and the wheel only count inside the client area, a release goes
+    /// wherever its press went, and moves are only sent while dragging.
+    fn broadcast_mouse(&mut self, followers: &[(Window, KeyMap)], leader: Rect, event: MouseEvent) {
+        let (x, y) = event.position();
+        // Like keys, presses wait for the pause to be over and releases don't
+        let inside = leader.contains_point(x, y) && !self.paused;
+
+        let recipients = match event {
+            MouseEvent::Button {
+                button, down: true, ..
+            } if inside => {
+                let group =
+                    groups::key_group(&self.config, self.active_group.as_deref(), button.key());
+                let hwnds = self.followers_in(followers, group);
+                self.mouse_buttons.insert(button, hwnds.clone());
+                hwnds
+            }
+            MouseEvent::Button {
+                button,
+                down: false,
+                ..
+            } => self.mouse_buttons.remove(&button).unwrap_or_default(),
+            MouseEvent::Move { .. } => {
+                let mut hwnds = self
+                    .mouse_buttons
+                    .values()
+                    .flatten()
+                    .copied()
+                    .collect::<Vec<_>>();
+                hwnds.sort_by_key(|hwnd| hwnd.0);
+                hwnds.dedup();
+                hwnds
+            }
+            MouseEvent::Wheel { .. } if inside => {
+                self.followers_in(followers, self.active_group.as_deref())
+            }
+            _ => Vec::new(),
+        };
+
+        self.send_mouse_hwnds(event, leader, &recipients);
+    }
+
+    /// Followers in `group`, all of them when `None`
+    fn followers_in(&self, followers: &[(Window, KeyMap)], group: Option<&str>) -> Vec<HWND> {
+        let members = followers
+            .iter()
+            .map(|(window, _)| Member {
+                title: &window.title,
+                slot: window.slot,
+            })
+            .collect::<Vec<_>>();
+
+        followers
+            .iter()
+            .zip(groups::members(&self.config, group, &members))
+            .filter(|(_, member)| *member)
+            .map(|((window, _), _)| window.hwnd)
+            .collect()
+    }
+
+    /// Sends the timed repeats of the held keys that are due `now`
+    fn repeat_held(&mut self, now: Instant) {
+        let mut timelines = Vec::new();
+        let mut repeated = Vec::new();
+        for (key, sent) in &self.keyboard {
+            for (i, sent) in sent.iter().enumerate() {
+                let due = sent.repeat.due(now - sent.pressed);
+                if due > sent.repeats {
+                    // Repeats piled up since the last tick go in one keystroke, like the system does
+                    let count = (due - sent.repeats).min(u16::MAX as u32) as u16;
+                    timelines.extend(self.repeat_timeline(sent, count));
+                    repeated.push((*key, i, due));
+                }
+            }
         }
 
-        // for key in [VK_LBUTTON, VK_RBUTTON, VK_MBUTTON].iter() {
-        //     let state = unsafe { GetAsyncKeyState(i32::from(key.0)) };
-        //     if state & (1 << (i16::BITS - 1)) != 0 {
-        //         let mut mouse_pos = POINT::default();
-        //         unsafe {
-        //             GetCursorPos(&mut mouse_pos);
-        //         }
+        self.send_key_hwnds(timelines);
+        for (key, i, due) in repeated {
+            if let Some(sent) = self.keyboard.get_mut(&key) {
+                sent[i].repeats = due;
+            }
+        }
+    }
 
-        //         dbg!(mouse_pos);
+    /// Autorepeat of what a follower was sent for a held key, `None` while a
+    /// chord lifts that key on the follower
+    fn repeat_timeline(&self, sent: &Sent, count: u16) -> Option<Timeline> {
+        let lifted =
+            self.keyboard.values().flatten().any(|other| {
+                other.hwnd == sent.hwnd && other.framing.lifted.contains(&sent.chord.key)
+            });
+        if lifted {
+            return None;
+        }
 
-        //         self.send_mouse_hwnds(
-        //             WPARAM(key.0 as usize),
-        //             mouse_pos.x,
-        //             mouse_pos.y,
-        //             &other_hwnds,
-        //         )
-        //     }
-        // }
+        Some(Timeline {
+            hwnd: sent.hwnd,
+            held: sent.framing.inside(&self.follower_keys(sent.hwnd)),
+            strokes: vec![Stroke {
+                count,
+                ..Stroke::now(sent.chord.key, true)
+            }],
+        })
     }
 
     pub fn swap_windows(&mut self) -> bool {
@@ -237,12 +1047,13 @@ impl App {
             return false;
         }
 
-        let foreground_hwnd = unsafe { GetForegroundWindow() };
+        let foreground_hwnd = self.platform.foreground_window();
         if !self.has_hwnd(foreground_hwnd) {
             return false;
         }
 
         let current_main_hwnd = self.main_hwnd.unwrap();
+        self.touch(&[foreground_hwnd, current_main_hwnd]);
         let mut windows = self
             .windows
             .iter_mut()
@@ -252,15 +1063,13 @@ impl App {
         let current = windows.pop().unwrap();
         let main = windows.pop().unwrap();
 
-        MoveWindowRect(main.hwnd, &current.rect, true);
-        MoveWindowRect(current.hwnd, &main.rect, true);
+        self.platform.move_window(main.hwnd, current.rect, true);
+        self.platform.move_window(current.hwnd, main.rect, true);
 
         std::mem::swap(&mut main.rect, &mut current.rect);
         self.main_hwnd = Some(foreground_hwnd);
 
-        unsafe {
-            SetForegroundWindow(foreground_hwnd);
-        }
+        self.platform.set_foreground_window(foreground_hwnd);
 
         true
     }
@@ -270,189 +1079,1634 @@ impl App {
             return;
         }
 
-        let mut desktop_rect = RECT::default();
-        let c_void_desktop_rect = &mut desktop_rect as *mut RECT as *mut _;
-        unsafe {
-            SystemParametersInfo(
-                SPI_GETWORKAREA,
-                0,
-                Some(c_void_desktop_rect),
-                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
-            );
-        }
-
-        let RECT {
-            left: _,
-            top: _,
-            right: main_width,
-            bottom: main_height,
-        } = desktop_rect;
-
-        let mut i = 0i32;
-
-        let small_width = main_width / (self.windows.len() - 1) as i32;
-        let small_height = main_height / self.windows.len() as i32;
-
-        for window in self.windows.iter_mut() {
-            if window.hwnd == self.main_hwnd.unwrap() {
-                window.rect = RECT {
-                    left: 0,
-                    top: 0,
-                    right: main_width,
-                    bottom: main_height - small_height,
-                };
-            } else {
-                window.rect = RECT {
-                    left: i * small_width,
-                    top: main_height - small_height,
-                    right: small_width,
-                    bottom: small_height,
-                };
-                i += 1;
+        let monitors = self.platform.monitors();
+        let main_hwnd = self.main_hwnd.unwrap();
+        let main = self
+            .windows
+            .iter()
+            .position(|w| w.hwnd == main_hwnd)
+            .unwrap_or(0);
+
+        // The windows are sorted by slot and take the cells in that order: a
+        // high slot number, or a missing client, leaves no empty cell
+        let rects = layout::layout(
+            &self.config.layout_strategy,
+            &monitors,
+            self.config.layout_monitors,
+            self.windows.len(),
+            main,
+        );
+        let moved = self
+            .windows
+            .iter()
+            .zip(&rects)
+            .filter(|(_, rect)| rect.is_some())
+            .map(|(window, _)| window.hwnd)
+            .collect::<Vec<_>>();
+        self.touch(&moved);
+        for (window, rect) in self.windows.iter_mut().zip(rects) {
+            let Some(rect) = rect else {
+                continue;
             };
+            window.rect = rect;
 
-            let rect = window.rect;
-            set_borders(window.hwnd, false);
-            MoveWindowRect(window.hwnd, &rect, true);
+            set_borders(&self.platform, window.hwnd, false);
+            self.platform.move_window(window.hwnd, rect, true);
         }
     }
 
     pub fn foreground(&self) {
         for window in self.windows.iter() {
             // Sets the window to be foreground always on top
-            SetWindowLevel(window.hwnd, HWND_TOPMOST);
+            self.platform.set_window_level(window.hwnd, HWND_TOPMOST);
             // Sets the window to be a normal window again
-            SetWindowLevel(window.hwnd, HWND_NOTOPMOST);
+            self.platform.set_window_level(window.hwnd, HWND_NOTOPMOST);
         }
     }
 
-    pub fn send_key_down(&self, key: WPARAM) {
-        self.send_key_hwnds(WM_KEYDOWN, key, &self.windows);
+    pub fn send_key_down(&mut self, key: Key) {
+        self.send_key_windows(Stroke::now(key, true));
     }
 
-    pub fn send_key_up(&self, key: WPARAM) {
-        self.send_key_hwnds(WM_KEYUP, key, &self.windows);
+    pub fn send_key_up(&mut self, key: Key) {
+        self.send_key_windows(Stroke::now(key, false));
     }
 
-    fn send_key_hwnds(&self, message: u32, key: WPARAM, hwnds: &[Window]) {
-        // https://learn.microsoft.com/en-us/windows/win32/inputdev/about-keyboard-input#keystroke-message-flags
-        let mut flags = (key.0 as u32) << 16;
-        if self.keyboard.contains(&key.0) {
-            flags |= KF_REPEAT;
-        }
+    fn send_key_windows(&mut self, stroke: Stroke) {
+        let timelines = self
+            .windows
+            .iter()
+            .map(|window| Timeline {
+                hwnd: window.hwnd,
+                held: Vec::new(),
+                strokes: vec![stroke],
+            })
+            .collect();
+        self.send_key_hwnds(timelines);
+    }
 
-        let lparam = LPARAM(flags as isize);
-        for window in hwnds.iter() {
-            unsafe {
-                PostMessage(window.hwnd, message, key, lparam);
-            };
+    /// Queues the timelines of several windows, every stroke is posted when it
+    /// is due, in the order of the timelines when they are due at once
+    fn send_key_hwnds(&mut self, timelines: Vec<Timeline>) {
+        let start = Instant::now();
+
+        for timeline in timelines {
+            let mut held = timeline.held;
+            for stroke in timeline.strokes {
+                let modifiers = held
+                    .iter()
+                    .fold(Modifiers::of(stroke.key), |modifiers, held| {
+                        modifiers.union(Modifiers::of(*held))
+                    });
+                let sent = (timeline.hwnd, stroke.key);
+                // A press of a key the window already holds is a repeat for it
+                let repeat = stroke.down && self.keys_down.contains(&sent);
+                let (message, mut lparam) =
+                    keystroke::keystroke(stroke.key, stroke.down, repeat, modifiers);
+                if stroke.down {
+                    lparam.repeat_count = stroke.count;
+                }
+                self.send_key(
+                    timeline.hwnd,
+                    message,
+                    stroke.key,
+                    lparam,
+                    start + stroke.at,
+                );
 
-            // TODO: Add random
-            std::thread::sleep(std::time::Duration::from_millis(10));
+                if stroke.down {
+                    held.push(stroke.key);
+                    if !self.keys_down.contains(&sent) {
+                        self.keys_down.push(sent);
+                    }
+                } else {
+                    held.retain(|held| *held != stroke.key);
+                    self.keys_down.retain(|down| *down != sent);
+                }
+            }
         }
     }
 
-    pub fn send_mouse(&self, key: WPARAM, x: i32, y: i32) {
-        self.send_mouse_hwnds(key, x, y, &self.windows);
+    fn send_key(&self, hwnd: HWND, message: u32, key: Key, lparam: KeyLParam, due: Instant) {
+        self.dispatcher.send(Job {
+            hwnd,
+            slot: self.slot(hwnd),
+            message,
+            wparam: WPARAM(key as usize),
+            lparam: lparam.into(),
+            due,
+        });
     }
 
-    fn send_mouse_hwnds(&self, _key: WPARAM, x: i32, y: i32, hwnds: &[Window]) {
-        let make_lparam = |x: i32, y: i32| LPARAM((x | (y << 16)) as isize);
-
-        let lparam_mousedown = make_lparam(x, y);
-        let _lparam_mouseup = make_lparam(x + 1, y + 1);
+    fn slot(&self, hwnd: HWND) -> Option<usize> {
+        self.windows
+            .iter()
+            .find(|window| window.hwnd == hwnd)
+            .map(|window| window.slot)
+    }
 
-        for window in hwnds.iter() {
-            unsafe {
-                PostMessage(
-                    window.hwnd,
-                    WM_LBUTTONDOWN,
-                    WPARAM(MK_LBUTTON.0 as usize),
-                    lparam_mousedown,
-                );
-                PostMessage(window.hwnd, WM_LBUTTONUP, WPARAM(0), lparam_mousedown);
-            };
+    /// Waits until every message sent so far is posted
+    pub fn flush(&self) {
+        self.dispatcher.flush();
+    }
 
-            // TODO: Add random
-            std::thread::sleep(std::time::Duration::from_millis(10));
-        }
+    /// How far behind the windows are
+    pub fn dispatch_stats(&self) -> DispatchStats {
+        self.dispatcher.stats()
+    }
+
+    /// Modifier keys down on the follower, sent for held leader keys and not lifted by a chord
+    fn follower_keys(&self, hwnd: HWND) -> Vec<Key> {
+        let sent = self
+            .keyboard
+            .values()
+            .flatten()
+            .filter(|sent| sent.hwnd == hwnd)
+            .collect::<Vec<_>>();
+
+        let mut keys = sent
+            .iter()
+            .map(|sent| sent.chord.key)
+            .filter(|key| Modifiers::of(*key) != Modifiers::NONE)
+            .chain(
+                sent.iter()
+                    .flat_map(|sent| sent.framing.pressed.iter().copied()),
+            )
+            .collect::<Vec<_>>();
+        keys.retain(|key| !sent.iter().any(|sent| sent.framing.lifted.contains(key)));
+        keys
+    }
+
+    /// Mirrors `event`, in screen coordinates over the main window, on every other window
+    pub fn send_mouse(&self, event: MouseEvent) {
+        let Some(main_hwnd) = self.main_hwnd else {
+            return;
+        };
+        let hwnds = self
+            .windows
+            .iter()
+            .map(|window| window.hwnd)
+            .filter(|hwnd| *hwnd != main_hwnd)
+            .collect::<Vec<_>>();
+
+        self.send_mouse_hwnds(event, self.platform.client_rect(main_hwnd), &hwnds);
+    }
+
+    /// Sends `event`, which happened over the `leader` client area, at the same
+    /// place of the client area of every window
+    fn send_mouse_hwnds(&self, event: MouseEvent, leader: Rect, hwnds: &[HWND]) {
+        let (x, y) = event.position();
+        let held = self.leader_held();
+        let now = Instant::now();
+
+        for hwnd in hwnds {
+            let buttons = self
+                .mouse_buttons
+                .iter()
+                .filter(|(_, hwnds)| hwnds.contains(hwnd))
+                .map(|(button, _)| *button)
+                .collect::<Vec<_>>();
+
+            let client = self.platform.client_rect(*hwnd);
+            let point = mouse::map_point(
+                self.config.mouse.mapping,
+                leader,
+                client,
+                (x - leader.x, y - leader.y),
+            );
+            let screen = (client.x + point.0, client.y + point.1);
+
+            for (message, wparam, lparam) in mouse::messages(&event, &buttons, held, point, screen)
+            {
+                self.dispatcher.send(Job {
+                    hwnd: *hwnd,
+                    slot: self.slot(*hwnd),
+                    message,
+                    wparam,
+                    lparam,
+                    due: now,
+                });
+            }
+        }
+    }
+}
+
+/// Nothing is left held on the followers, even when unwinding from a panic
+impl<P: Platform> Drop for App<P> {
+    fn drop(&mut self) {
+        self.release_all();
     }
 }
 
-impl eframe::App for App {
+impl<P: Platform> eframe::App for App<P> {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("World of Warcraft are being mimic-ed");
+
+            let pause_key = match self.config.pause_key {
+                Some(key) => format!(" ({:?})", key),
+                None => String::new(),
+            };
+            if self.paused {
+                ui.label(format!("Broadcast paused{}", pause_key));
+                if ui.button("Resume").clicked() {
+                    self.paused = false;
+                }
+            } else {
+                if self.is_chatting() {
+                    ui.label("Typing in chat, nothing is broadcast");
+                }
+                if ui.button(format!("Pause{}", pause_key)).clicked() {
+                    self.paused = true;
+                }
+            }
+
             if ui.button("Clear").clicked() {
                 self.windows.clear();
             }
 
             if ui.button("Get all windows").clicked() {
-                self.update_windows("warcraft")
+                if let Err(e) = self.update_windows() {
+                    ui.label(e.to_string());
+                }
             }
         });
     }
 }
 
-#[allow(non_snake_case)]
-pub fn SetWindowLevel(hwnd: HWND, level: HWND) {
-    unsafe {
-        SetWindowPos(
-            hwnd,
-            level,
-            0,
-            0,
-            0,
-            0,
-            SWP_SHOWWINDOW | SWP_NOSIZE | SWP_NOMOVE,
-        );
-    }
-}
-
-#[allow(non_snake_case)]
-pub fn MoveWindowRect(hwnd: HWND, rect: &RECT, repaint: bool) {
-    unsafe {
-        MoveWindow(hwnd, rect.left, rect.top, rect.right, rect.bottom, repaint);
-    }
+/// Whether the window has a title bar, as `set_borders` leaves it
+pub fn has_borders<P: Platform>(platform: &P, hwnd: HWND) -> bool {
+    platform.window_style(hwnd).style & WS_CAPTION.0 as isize != 0
 }
 
-pub fn set_borders(hwnd: HWND, borders: bool) {
-    let [mut l_style, mut l_ex_style] = [
-        unsafe { GetWindowLong(hwnd, GWL_STYLE) }, // Forces clippy to put them once per line
-        unsafe { GetWindowLong(hwnd, GWL_EXSTYLE) },
-    ];
+pub fn set_borders<P: Platform>(platform: &P, hwnd: HWND, borders: bool) {
+    let mut style = platform.window_style(hwnd);
 
     let lstyle_mask =
         (WS_CAPTION | WS_THICKFRAME | WS_MINIMIZEBOX | WS_MAXIMIZEBOX | WS_SYSMENU).0 as isize;
     let l_ex_style_mask = (WS_EX_DLGMODALFRAME | WS_EX_CLIENTEDGE | WS_EX_STATICEDGE).0 as isize;
 
     if !borders {
-        l_style &= !lstyle_mask;
-        l_ex_style &= !l_ex_style_mask;
+        style.style &= !lstyle_mask;
+        style.ex_style &= !l_ex_style_mask;
     } else {
-        l_style |= lstyle_mask;
-        l_ex_style |= l_ex_style_mask;
+        style.style |= lstyle_mask;
+        style.ex_style |= l_ex_style_mask;
     }
 
-    let uflags = SWP_FRAMECHANGED | SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER | SWP_NOOWNERZORDER;
-    unsafe {
-        SetWindowLong(hwnd, GWL_STYLE, l_style);
-        SetWindowLong(hwnd, GWL_EXSTYLE, l_ex_style);
-        SetWindowPos(hwnd, HWND(0), 0, 0, 0, 0, uflags);
-    };
+    platform.set_window_style(hwnd, style);
 }
 
-fn get_rect(hwnd: HWND) -> RECT {
-    let mut rect1 = RECT::default();
-    let mut rect2 = RECT::default();
-    unsafe {
-        GetWindowRect(hwnd, &mut rect1);
-        GetClientRect(hwnd, &mut rect2);
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use windows::messaging::{
+        WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MOUSEMOVE, WM_MOUSEWHEEL,
+        WM_SYSKEYDOWN, WM_SYSKEYUP,
+    };
+    use windows::LPARAM;
+
+    use super::*;
+    use crate::platform::PostedMessage;
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect::new(x, y, width, height)
+    }
+
+    fn config() -> Config {
+        Config {
+            state_file: None,
+            slots: SlotOptions {
+                file: None,
+                ..SlotOptions::default()
+            },
+            // Never hook the real keyboard, the fake platform is polled
+            input: InputMode::Poll,
+            ..Config::default()
+        }
+    }
+
+    fn setup() -> (FakePlatform, Vec<HWND>) {
+        let platform = FakePlatform::new();
+        let hwnds = vec![
+            platform.add_window("World of Warcraft", rect(0, 0, 800, 600)),
+            platform.add_window("Discord", rect(0, 0, 800, 600)),
+            platform.add_window("World of Warcraft", rect(0, 0, 800, 600)),
+            platform.add_window("World of Warcraft", rect(0, 0, 800, 600)),
+        ];
+
+        (platform, hwnds)
+    }
+
+    #[test]
+    fn update_windows_matches_titles() {
+        let (platform, hwnds) = setup();
+        let app = App::new(platform, config()).unwrap();
+
+        assert_eq!(app.windows.len(), 3);
+        assert!(!app.has_hwnd(hwnds[1]));
+        assert_eq!(app.main_hwnd, Some(hwnds[0]));
+    }
+
+    #[test]
+    fn update_windows_uses_the_matcher_spec() {
+        let (platform, hwnds) = setup();
+        let tab = platform.add_window(
+            "World of Warcraft - Wowhead - Firefox",
+            rect(0, 0, 800, 600),
+        );
+        for hwnd in [hwnds[0], hwnds[2], hwnds[3]] {
+            platform.set_window_process(hwnd, 200_000 + hwnd.0 as u32, "Wow.exe");
+        }
+        platform.set_window_process(tab, 300_000, "firefox.exe");
+        platform.set_window_visible(hwnds[3], false);
+
+        let config = Config {
+            window_name: WindowName::Spec(MatcherSpec {
+                executable: Some("wow.exe".into()),
+                visible_only: true,
+                ..MatcherSpec::default()
+            }),
+            ..config()
+        };
+        let app = App::new(platform, config).unwrap();
+
+        assert_eq!(
+            app.windows.iter().map(|w| w.hwnd).collect::<Vec<_>>(),
+            vec![hwnds[0], hwnds[2]]
+        );
+    }
+
+    #[test]
+    fn mimic_sends_to_followers_only() {
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), config()).unwrap();
+
+        platform.set_foreground_window(hwnds[0]);
+        platform.press(Key::VK_1);
+        app.mimic();
+
+        app.flush();
+        let posted = platform.take_posted();
+        assert_eq!(posted.len(), 2);
+        assert!(posted.iter().all(|m| m.message == WM_KEYDOWN));
+        assert!(posted.iter().all(|m| m.wparam == Key::VK_1 as usize));
+        assert!(posted.iter().all(|m| m.hwnd != hwnds[0]));
+
+        platform.release(Key::VK_1);
+        app.mimic();
+
+        app.flush();
+        let posted = platform.take_posted();
+        assert_eq!(posted.len(), 2);
+        assert!(posted.iter().all(|m| m.message == WM_KEYUP));
+    }
+
+    #[test]
+    fn mimic_applies_profiles_per_window() {
+        let platform = FakePlatform::new();
+        let tank = platform.add_window("World of Warcraft - Tank", rect(0, 0, 800, 600));
+        let healer = platform.add_window("World of Warcraft - Healer", rect(0, 0, 800, 600));
+        let mage = platform.add_window("World of Warcraft - Mage", rect(0, 0, 800, 600));
+
+        let config = Config {
+            profiles: vec![
+                Profile {
+                    title: Some("healer".into()),
+                    remap_keybind: HashMap::from([(Key::VK_1.into(), Key::VK_5.into())]),
+                    ..Profile::default()
+                },
+                Profile {
+                    slot: Some(2),
+                    skip_keybind: vec![Key::VK_1.into()],
+                    ..Profile::default()
+                },
+            ],
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+
+        platform.set_foreground_window(tank);
+        platform.press(Key::VK_1);
+        app.mimic();
+
+        app.flush();
+        let posted = platform
+            .take_posted()
+            .into_iter()
+            .map(|m| (m.hwnd, m.wparam))
+            .collect::<Vec<_>>();
+        assert_eq!(posted, vec![(healer, Key::VK_5 as usize)]);
+        assert!(!posted.iter().any(|(hwnd, _)| *hwnd == mage));
+    }
+
+    #[test]
+    fn shortcuts_switch_the_broadcast_group() {
+        let (platform, hwnds) = setup();
+        let config = Config {
+            groups: HashMap::from([(
+                "casters".to_string(),
+                vec![Selector {
+                    slot: Some(2),
+                    ..Selector::default()
+                }],
+            )]),
+            shortcuts: HashMap::from([
+                (Key::VK_G, config::Shortcut::Group("casters".into())),
+                (Key::VK_H, config::Shortcut::AllFollowers),
+            ]),
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+        platform.set_foreground_window(hwnds[0]);
+
+        let targets = |app: &mut App<FakePlatform>| {
+            platform.press(Key::VK_1);
+            app.mimic();
+            platform.release(Key::VK_1);
+            app.mimic();
+            app.flush();
+            platform
+                .take_posted()
+                .into_iter()
+                .filter(|m| m.message == WM_KEYDOWN)
+                .map(|m| m.hwnd)
+                .collect::<Vec<_>>()
+        };
+        let shortcut = |app: &mut App<FakePlatform>, key| {
+            platform.press(Key::VK_LSHIFT);
+            platform.press(Key::VK_LMENU);
+            platform.press(key);
+            app.global_shortcuts();
+            for key in [Key::VK_LSHIFT, Key::VK_LMENU, key] {
+                platform.release(key);
+            }
+        };
+
+        assert_eq!(targets(&mut app), vec![hwnds[2], hwnds[3]]);
+
+        shortcut(&mut app, Key::VK_G);
+        assert_eq!(targets(&mut app), vec![hwnds[3]]);
+
+        shortcut(&mut app, Key::VK_H);
+        assert_eq!(targets(&mut app), vec![hwnds[2], hwnds[3]]);
+    }
+
+    #[test]
+    fn mimic_posts_real_keystroke_flags() {
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), config()).unwrap();
+        platform.set_foreground_window(hwnds[0]);
+
+        platform.press(Key::VK_UP);
+        app.mimic();
+        app.mimic();
+        platform.release(Key::VK_UP);
+        app.mimic();
+
+        app.flush();
+        let lparams = platform
+            .take_posted()
+            .into_iter()
+            .filter(|m| m.hwnd == hwnds[2])
+            .map(|m| (m.message, m.lparam))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lparams,
+            vec![
+                (WM_KEYDOWN, 0x0148_0001),
+                (WM_KEYDOWN, 0x4148_0001),
+                (WM_KEYUP, 0xC148_0001),
+            ]
+        );
+    }
+
+    #[test]
+    fn mimic_sends_alt_chords_as_system_keys() {
+        use windows::messaging::{WM_SYSKEYDOWN, WM_SYSKEYUP};
+
+        let (platform, hwnds) = setup();
+        let alt = Modifiers {
+            alt: true,
+            ..Modifiers::NONE
+        };
+        let config = Config {
+            keybind: vec![Key::VK_1.into(), Key::VK_RMENU.into()],
+            remap_keybind: HashMap::from([(
+                Chord::new(alt, Key::VK_1),
+                Chord::new(alt, Key::VK_2).into(),
+            )]),
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+        platform.set_foreground_window(hwnds[0]);
+
+        let repeat = LPARAM::from(KeyLParam::key_down(Key::VK_RMENU, true).with_context(true)).0;
+        let sent = |platform: &FakePlatform| {
+            platform
+                .take_posted()
+                .into_iter()
+                .filter(|m| m.hwnd == hwnds[2])
+                // Autorepeat of the held Alt
+                .filter(|m| m.lparam != repeat)
+                .map(|m| (m.message, m.wparam, m.lparam & 0x2000_0000 != 0))
+                .collect::<Vec<_>>()
+        };
+
+        // Left Alt is not sent, so Alt is pressed for the follower around the 2
+        platform.press(Key::VK_LMENU);
+        platform.press(Key::VK_1);
+        app.mimic();
+        platform.release(Key::VK_1);
+        platform.release(Key::VK_LMENU);
+        app.mimic();
+        app.flush();
+        assert_eq!(
+            sent(&platform),
+            vec![
+                (WM_SYSKEYDOWN, Key::VK_LMENU as usize, true),
+                (WM_SYSKEYDOWN, Key::VK_2 as usize, true),
+                (WM_SYSKEYUP, Key::VK_2 as usize, true),
+                (WM_SYSKEYUP, Key::VK_LMENU as usize, true),
+            ]
+        );
+
+        // Right Alt is sent and already held by the follower, it is not pressed twice
+        platform.press(Key::VK_RMENU);
+        app.mimic();
+        platform.press(Key::VK_1);
+        app.mimic();
+        platform.release(Key::VK_1);
+        app.mimic();
+        platform.release(Key::VK_RMENU);
+        app.mimic();
+        app.flush();
+        assert_eq!(
+            sent(&platform),
+            vec![
+                (WM_SYSKEYDOWN, Key::VK_RMENU as usize, true),
+                (WM_SYSKEYDOWN, Key::VK_2 as usize, true),
+                (WM_SYSKEYUP, Key::VK_2 as usize, true),
+                (WM_SYSKEYUP, Key::VK_RMENU as usize, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn mimic_swaps_modifier_layers_for_chorded_remaps() {
+        let (platform, hwnds) = setup();
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::NONE
+        };
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::NONE
+        };
+        let config = Config {
+            keybind: vec![Key::VK_LSHIFT.into(), Key::VK_F1.into()],
+            remap_keybind: HashMap::from([(
+                Chord::new(shift, Key::VK_F1),
+                Chord::new(ctrl, Key::VK_3).into(),
+            )]),
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+        platform.set_foreground_window(hwnds[0]);
+
+        let shift_repeat = LPARAM::from(KeyLParam::key_down(Key::VK_LSHIFT, true)).0;
+        let sent = |platform: &FakePlatform| {
+            platform
+                .take_posted()
+                .into_iter()
+                .filter(|m| m.hwnd == hwnds[2] && m.lparam != shift_repeat)
+                .map(|m| (m.message, m.wparam))
+                .collect::<Vec<_>>()
+        };
+        let key = |message, key: Key| (message, key as usize);
+
+        platform.press(Key::VK_LSHIFT);
+        app.mimic();
+        platform.press(Key::VK_F1);
+        app.mimic();
+        platform.release(Key::VK_F1);
+        app.mimic();
+        platform.release(Key::VK_LSHIFT);
+        app.mimic();
+        // F1 alone is not remapped
+        platform.press(Key::VK_F1);
+        app.mimic();
+        platform.release(Key::VK_F1);
+        app.mimic();
+
+        app.flush();
+        assert_eq!(
+            sent(&platform),
+            vec![
+                key(WM_KEYDOWN, Key::VK_LSHIFT),
+                key(WM_KEYUP, Key::VK_LSHIFT),
+                key(WM_KEYDOWN, Key::VK_LCONTROL),
+                key(WM_KEYDOWN, Key::VK_3),
+                key(WM_KEYUP, Key::VK_3),
+                key(WM_KEYUP, Key::VK_LCONTROL),
+                key(WM_KEYDOWN, Key::VK_LSHIFT),
+                key(WM_KEYUP, Key::VK_LSHIFT),
+                key(WM_KEYDOWN, Key::VK_F1),
+                key(WM_KEYUP, Key::VK_F1),
+            ]
+        );
+    }
+
+    #[test]
+    fn mimic_types_sequences_once_per_press() {
+        let (platform, hwnds) = setup();
+        let steps = vec![
+            Step::Tap(Key::VK_RETURN.into()),
+            Step::Delay(config::Duration::Milliseconds(100)),
+            Step::Tap(Key::VK_F.into()),
+            Step::Tap(Key::VK_RETURN.into()),
+        ];
+        let config = Config {
+            keybind: vec![Key::VK_1.into()],
+            remap_keybind: HashMap::from([(Key::VK_F.into(), Remap::Sequence(steps))]),
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+        platform.set_foreground_window(hwnds[0]);
+
+        let started = Instant::now();
+        platform.press(Key::VK_F);
+        app.mimic();
+        // Held and released, nothing more is typed
+        app.mimic();
+        platform.release(Key::VK_F);
+        app.mimic();
+
+        app.flush();
+        let posted = platform.take_posted();
+        let keys = |hwnd| {
+            posted
+                .iter()
+                .filter(|m| m.hwnd == hwnd)
+                .map(|m| (m.message, m.wparam))
+                .collect::<Vec<_>>()
+        };
+        let key = |message, key: Key| (message, key as usize);
+        let typed = vec![
+            key(WM_KEYDOWN, Key::VK_RETURN),
+            key(WM_KEYUP, Key::VK_RETURN),
+            key(WM_KEYDOWN, Key::VK_F),
+            key(WM_KEYUP, Key::VK_F),
+            key(WM_KEYDOWN, Key::VK_RETURN),
+            key(WM_KEYUP, Key::VK_RETURN),
+        ];
+        assert_eq!(keys(hwnds[2]), typed);
+        assert_eq!(keys(hwnds[3]), typed);
+
+        // The steps after the delay were due 100ms after the press
+        let delay = std::time::Duration::from_millis(100);
+        for hwnd in [hwnds[2], hwnds[3]] {
+            let after = posted
+                .iter()
+                .filter(|m| m.hwnd == hwnd)
+                .skip(2)
+                .all(|m| m.at >= started + delay);
+            assert!(after);
+        }
+
+        // The delay is waited for by the dispatcher, not by mimic: both
+        // followers got the first Enter before either waited
+        let first_enters = posted
+            .iter()
+            .take(4)
+            .filter(|m| m.wparam == Key::VK_RETURN as usize)
+            .count();
+        assert_eq!(first_enters, 4);
+    }
+
+    #[test]
+    fn mimic_broadcasts_taps_between_two_ticks() {
+        let (platform, hwnds) = setup();
+        let config = Config {
+            keybind: vec![Key::VK_1.into(), Key::VK_LSHIFT.into()],
+            remap_keybind: HashMap::from([(
+                Chord::new(
+                    Modifiers {
+                        shift: true,
+                        ..Modifiers::NONE
+                    },
+                    Key::VK_1,
+                ),
+                Key::VK_2.into(),
+            )]),
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+        let typed = SyntheticInput::new();
+        app.set_input(typed.clone());
+        platform.set_foreground_window(hwnds[0]);
+
+        // Nothing is down as far as the platform knows, the events are all there is
+        typed.tap(Key::VK_1);
+        typed.press(Key::VK_LSHIFT);
+        typed.tap(Key::VK_1);
+        typed.release(Key::VK_LSHIFT);
+        app.mimic();
+        app.flush();
+
+        let sent = platform
+            .take_posted()
+            .into_iter()
+            .filter(|m| m.hwnd == hwnds[2])
+            .map(|m| (m.message, m.wparam))
+            .collect::<Vec<_>>();
+        let key = |message, key: Key| (message, key as usize);
+        assert_eq!(
+            sent,
+            vec![
+                key(WM_KEYDOWN, Key::VK_1),
+                key(WM_KEYUP, Key::VK_1),
+                key(WM_KEYDOWN, Key::VK_LSHIFT),
+                // Shift+1 is sent as a plain 2
+                key(WM_KEYUP, Key::VK_LSHIFT),
+                key(WM_KEYDOWN, Key::VK_2),
+                key(WM_KEYUP, Key::VK_2),
+                key(WM_KEYDOWN, Key::VK_LSHIFT),
+                key(WM_KEYUP, Key::VK_LSHIFT),
+            ]
+        );
+    }
+
+    #[test]
+    fn mimic_mirrors_clicks_drags_and_wheel() {
+        let (platform, hwnds) = setup();
+        // Half the size of the leader, further right on the screen
+        platform.move_window(hwnds[3], rect(1000, 100, 400, 300), false);
+        let config = Config {
+            mouse: MouseOptions {
+                enabled: true,
+                mapping: MouseMapping::Normalized,
+            },
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+        let moved = SyntheticInput::new();
+        app.set_input(moved.clone());
+        platform.set_foreground_window(hwnds[0]);
+
+        let button = |down, x, y| MouseEvent::Button {
+            button: MouseButton::Left,
+            down,
+            x,
+            y,
+        };
+        // Moving without a button and clicking outside the leader are not mirrored
+        moved.push_mouse(MouseEvent::Move { x: 10, y: 10 });
+        moved.click(MouseButton::Right, 900, 50);
+        moved.push_mouse(button(true, 400, 300));
+        moved.push_mouse(MouseEvent::Move { x: 600, y: 450 });
+        moved.push_mouse(button(false, 600, 450));
+        moved.push_mouse(MouseEvent::Wheel {
+            delta: 120,
+            x: 600,
+            y: 450,
+        });
+        app.mimic();
+        app.flush();
+
+        let posted = platform.take_posted();
+        let sent = |hwnd| {
+            posted
+                .iter()
+                .filter(|m| m.hwnd == hwnd)
+                .map(|m| (m.message, m.wparam, m.lparam))
+                .collect::<Vec<_>>()
+        };
+        let at = |x: isize, y: isize| (y << 16) | x;
+        assert_eq!(
+            sent(hwnds[3]),
+            vec![
+                (WM_MOUSEMOVE, 0, at(200, 150)),
+                (WM_LBUTTONDOWN, 0x01, at(200, 150)),
+                (WM_MOUSEMOVE, 0x01, at(300, 225)),
+                (WM_LBUTTONUP, 0, at(300, 225)),
+                // The wheel is in screen coordinates
+                (WM_MOUSEWHEEL, 120 << 16, at(1300, 325)),
+            ]
+        );
+        assert_eq!(sent(hwnds[2])[1], (WM_LBUTTONDOWN, 0x01, at(400, 300)));
+        assert!(sent(hwnds[0]).is_empty());
+    }
+
+    /// Keys a window is left holding once `posted` are all delivered
+    fn still_down(posted: &[PostedMessage], hwnd: HWND) -> Vec<usize> {
+        let mut down = Vec::new();
+        for m in posted.iter().filter(|m| m.hwnd == hwnd) {
+            match m.message {
+                WM_KEYDOWN | WM_SYSKEYDOWN if !down.contains(&m.wparam) => down.push(m.wparam),
+                WM_KEYUP | WM_SYSKEYUP => down.retain(|key| *key != m.wparam),
+                _ => {}
+            }
+        }
+        down
+    }
+
+    fn held_keys_config() -> Config {
+        Config {
+            keybind: vec![Key::VK_1.into(), Key::VK_2.into(), Key::VK_LSHIFT.into()],
+            remap_keybind: HashMap::from([(
+                Chord::new(
+                    Modifiers {
+                        shift: true,
+                        ..Modifiers::NONE
+                    },
+                    Key::VK_1,
+                ),
+                Chord::new(
+                    Modifiers {
+                        alt: true,
+                        ..Modifiers::NONE
+                    },
+                    Key::VK_3,
+                )
+                .into(),
+            )]),
+            mouse: MouseOptions {
+                enabled: true,
+                ..MouseOptions::default()
+            },
+            ..config()
+        }
+    }
+
+    #[test]
+    fn release_all_matches_every_down() {
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), held_keys_config()).unwrap();
+        let typed = SyntheticInput::new();
+        app.set_input(typed.clone());
+        platform.set_foreground_window(hwnds[0]);
+
+        typed.press(Key::VK_LSHIFT);
+        typed.press(Key::VK_1);
+        typed.press(Key::VK_2);
+        typed.push_mouse(MouseEvent::Button {
+            button: MouseButton::Left,
+            down: true,
+            x: 10,
+            y: 10,
+        });
+        app.mimic();
+        app.flush();
+        let mut posted = platform.take_posted();
+        assert_eq!(
+            still_down(&posted, hwnds[2]),
+            vec![
+                Key::VK_LMENU as usize,
+                Key::VK_3 as usize,
+                Key::VK_2 as usize
+            ]
+        );
+
+        app.release_all();
+        posted.extend(platform.take_posted());
+        for hwnd in &hwnds {
+            assert_eq!(still_down(&posted, *hwnd), vec![]);
+        }
+        let buttons = |message| {
+            posted
+                .iter()
+                .filter(|m| m.hwnd == hwnds[2] && m.message == message)
+                .count()
+        };
+        assert_eq!(buttons(WM_LBUTTONDOWN), buttons(WM_LBUTTONUP));
+
+        // Nothing is left to release
+        app.release_all();
+        assert_eq!(platform.take_posted(), vec![]);
+    }
+
+    #[test]
+    fn focus_loss_and_mode_switch_release_held_keys() {
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), held_keys_config()).unwrap();
+        let typed = SyntheticInput::new();
+        app.set_input(typed.clone());
+        platform.set_foreground_window(hwnds[0]);
+
+        typed.press(Key::VK_1);
+        app.mimic();
+        // The leader loses focus with the key still down, its release goes elsewhere
+        platform.set_foreground_window(hwnds[1]);
+        app.mimic();
+        app.flush();
+        let posted = platform.take_posted();
+        assert!(posted.iter().any(|m| m.message == WM_KEYDOWN));
+        assert_eq!(still_down(&posted, hwnds[2]), vec![]);
+
+        // Held when it comes back, the key is pressed again
+        platform.set_foreground_window(hwnds[0]);
+        app.mimic();
+        app.flush();
+        let mut posted = platform.take_posted();
+        assert_eq!(still_down(&posted, hwnds[2]), vec![]);
+        typed.press(Key::VK_1);
+        app.mimic();
+        app.flush();
+        posted.extend(platform.take_posted());
+        assert_eq!(still_down(&posted, hwnds[2]), vec![Key::VK_1 as usize]);
+
+        for key in [Key::VK_LSHIFT, Key::VK_LMENU, Key::VK_ESCAPE] {
+            platform.press(key);
+        }
+        assert!(app.global_shortcuts());
+        posted.extend(platform.take_posted());
+        assert_eq!(still_down(&posted, hwnds[2]), vec![]);
+    }
+
+    #[test]
+    fn dropping_the_app_releases_held_keys() {
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), held_keys_config()).unwrap();
+        let typed = SyntheticInput::new();
+        app.set_input(typed.clone());
+        platform.set_foreground_window(hwnds[0]);
+
+        typed.press(Key::VK_LSHIFT);
+        typed.press(Key::VK_1);
+        app.mimic();
+        drop(app);
+
+        let posted = platform.take_posted();
+        assert_eq!(
+            posted.iter().filter(|m| m.hwnd == hwnds[3]).count(),
+            // Shift, then Alt swapped in for it around 3, and all of them released
+            6
+        );
+        assert_eq!(still_down(&posted, hwnds[3]), vec![]);
+    }
+
+    #[test]
+    fn input_queued_outside_mimic_is_dropped() {
+        let (platform, hwnds) = setup();
+        let config = Config {
+            mode: Mode::Bot,
+            ..held_keys_config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+        let typed = SyntheticInput::new();
+        app.set_input(typed.clone());
+        platform.set_foreground_window(hwnds[0]);
+
+        // Nobody reads the input while the bot runs
+        typed.tap(Key::VK_1);
+        typed.press(Key::VK_2);
+        typed.click(MouseButton::Left, 10, 10);
+        typed.push_mouse(MouseEvent::Move { x: 20, y: 20 });
+
+        for key in [Key::VK_LSHIFT, Key::VK_LMENU, Key::VK_R] {
+            platform.press(key);
+        }
+        assert!(app.global_shortcuts());
+        assert!(matches!(app.config.mode, Mode::Mimic));
+        for key in [Key::VK_LSHIFT, Key::VK_LMENU, Key::VK_R] {
+            platform.release(key);
+        }
+
+        app.mimic();
+        app.flush();
+        assert_eq!(platform.take_posted(), vec![]);
+    }
+
+    #[test]
+    fn pause_key_and_chat_mode_hold_back_presses() {
+        let (platform, hwnds) = setup();
+        let config = Config {
+            chat_mode: true,
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+        let typed = SyntheticInput::new();
+        app.set_input(typed.clone());
+        platform.set_foreground_window(hwnds[0]);
+
+        let mut sent = || {
+            app.mimic();
+            app.flush();
+            platform
+                .take_posted()
+                .into_iter()
+                .filter(|m| m.hwnd == hwnds[2])
+                .map(|m| (m.message, m.wparam))
+                .collect::<Vec<_>>()
+        };
+        let key = |message, key: Key| (message, key as usize);
+
+        // Held through the pause, W is still released
+        typed.press(Key::VK_W);
+        typed.tap(Key::VK_SCROLL);
+        typed.tap(Key::VK_1);
+        typed.release(Key::VK_W);
+        assert_eq!(
+            sent(),
+            vec![key(WM_KEYDOWN, Key::VK_W), key(WM_KEYUP, Key::VK_W)]
+        );
+
+        typed.tap(Key::VK_SCROLL);
+        typed.tap(Key::VK_1);
+        typed.tap(Key::VK_RETURN);
+        typed.tap(Key::VK_2);
+        typed.tap(Key::VK_ESCAPE);
+        typed.tap(Key::VK_3);
+        assert_eq!(
+            sent(),
+            vec![
+                key(WM_KEYDOWN, Key::VK_1),
+                key(WM_KEYUP, Key::VK_1),
+                key(WM_KEYDOWN, Key::VK_3),
+                key(WM_KEYUP, Key::VK_3),
+            ]
+        );
+    }
+
+    #[test]
+    fn held_keys_repeat_per_follower_policy() {
+        let (platform, hwnds) = setup();
+        let config = Config {
+            keybind: vec![Key::VK_1.into()],
+            repeat: RepeatOptions {
+                default: Repeat::Timed {
+                    delay: config::Duration::Milliseconds(40),
+                    interval: config::Duration::Milliseconds(10),
+                },
+                slots: HashMap::from([(2, Repeat::None)]),
+            },
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+        platform.set_foreground_window(hwnds[0]);
+
+        // The poller reports the held key on every tick, the policy alone decides
+        let start = Instant::now();
+        let at = |ms| start + std::time::Duration::from_millis(ms);
+        platform.press(Key::VK_1);
+        app.mimic_at(at(0));
+        app.mimic_at(at(10));
+        app.mimic_at(at(75));
+        platform.release(Key::VK_1);
+        app.mimic_at(at(85));
+        app.flush();
+
+        // Message, repeat count and previous key state of every keystroke
+        let posted = platform.take_posted();
+        let sent = |hwnd| {
+            posted
+                .iter()
+                .filter(|m| m.hwnd == hwnd)
+                .map(|m| (m.message, m.lparam & 0xFFFF, m.lparam & (1 << 30) != 0))
+                .collect::<Vec<_>>()
+        };
+
+        // 35ms of repeats every 10ms go in one keystroke, which the window knows is a repeat
+        let timed = sent(hwnds[2]);
+        assert_eq!(timed.len(), 3);
+        assert_eq!(timed[0], (WM_KEYDOWN, 1, false));
+        assert_eq!(timed[1], (WM_KEYDOWN, 4, true));
+        assert_eq!(timed[2], (WM_KEYUP, 1, true));
+
+        assert_eq!(
+            sent(hwnds[3]),
+            vec![(WM_KEYDOWN, 1, false), (WM_KEYUP, 1, true)]
+        );
+    }
+
+    #[test]
+    fn shortcuts_fire_once_per_press_behind_the_prefix() {
+        let (platform, _) = setup();
+        let config = Config {
+            shortcuts: HashMap::from([(Key::VK_R, config::Shortcut::Mode(Mode::Mimic))]),
+            shortcut_prefix: vec![Key::VK_RCONTROL],
+            shortcut_debounce: config::Duration::Milliseconds(100),
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+
+        // Which ticks of a 10ms poll loop switched modes
+        let start = Instant::now();
+        let mut tick = 0;
+        let mut fired = Vec::new();
+        let mut poll = |app: &mut App<FakePlatform>, ticks| {
+            for _ in 0..ticks {
+                let now = start + std::time::Duration::from_millis(tick * 10);
+                if app.shortcuts_at(now) {
+                    fired.push(tick);
+                }
+                tick += 1;
+            }
+        };
+
+        // The old prefix does nothing anymore
+        for key in [Key::VK_LSHIFT, Key::VK_LMENU, Key::VK_R] {
+            platform.press(key);
+        }
+        poll(&mut app, 2);
+        platform.release(Key::VK_LSHIFT);
+        platform.release(Key::VK_LMENU);
+
+        // R was down before the prefix, it has to be pressed again
+        platform.press(Key::VK_RCONTROL);
+        poll(&mut app, 2);
+        platform.release(Key::VK_R);
+        poll(&mut app, 15);
+        platform.press(Key::VK_R);
+        poll(&mut app, 10);
+        // The contacts bounce on release
+        platform.release(Key::VK_R);
+        poll(&mut app, 1);
+        platform.press(Key::VK_R);
+        poll(&mut app, 1);
+        platform.release(Key::VK_R);
+        poll(&mut app, 15);
+        platform.press(Key::VK_R);
+        poll(&mut app, 1);
+
+        assert_eq!(fired, vec![19, 46]);
+    }
+
+    /// Presses and releases `key` behind the default prefix, and whether the loop has to restart
+    fn tap_shortcut(app: &mut App<FakePlatform>, platform: &FakePlatform, key: Key) -> bool {
+        platform.press(Key::VK_LSHIFT);
+        platform.press(Key::VK_LMENU);
+        platform.press(key);
+        let update = app.global_shortcuts();
+        for key in [key, Key::VK_LMENU, Key::VK_LSHIFT] {
+            platform.release(key);
+        }
+        app.global_shortcuts();
+        update
     }
 
-    RECT {
-        bottom: rect2.bottom,
-        right: rect2.right,
-        ..rect1
+    fn shortcuts_config() -> Config {
+        Config {
+            shortcut_debounce: config::Duration::Milliseconds(0),
+            ..config()
+        }
+    }
+
+    #[test]
+    fn shortcuts_are_not_broadcast() {
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), shortcuts_config()).unwrap();
+        let typed = SyntheticInput::new();
+        app.set_input(typed.clone());
+        platform.set_foreground_window(hwnds[0]);
+
+        for key in [Key::VK_LSHIFT, Key::VK_LMENU, Key::VK_T] {
+            platform.press(key);
+            typed.press(key);
+        }
+        app.mimic();
+        assert!(!app.global_shortcuts());
+        // Held, the keys repeat
+        for key in [Key::VK_LSHIFT, Key::VK_LMENU, Key::VK_T] {
+            typed.press(key);
+        }
+        app.mimic();
+        for key in [Key::VK_T, Key::VK_LMENU, Key::VK_LSHIFT] {
+            platform.release(key);
+            typed.release(key);
+        }
+        app.mimic();
+        app.global_shortcuts();
+
+        app.flush();
+        assert_eq!(platform.take_posted(), vec![]);
+        assert!(!has_borders(&platform, hwnds[2]));
+    }
+
+    #[test]
+    fn shortcuts_cycle_and_focus_the_leader() {
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), shortcuts_config()).unwrap();
+        platform.set_foreground_window(hwnds[0]);
+
+        let mut leaders = Vec::new();
+        for key in [Key::VK_N, Key::VK_N, Key::VK_N, Key::VK_P, Key::VK_F2] {
+            tap_shortcut(&mut app, &platform, key);
+            leaders.push(platform.foreground_window());
+        }
+
+        // Discord is not a client, the clients wrap around in slot order
+        assert_eq!(
+            leaders,
+            vec![hwnds[2], hwnds[3], hwnds[0], hwnds[3], hwnds[2]]
+        );
+        assert_eq!(app.main_hwnd, Some(hwnds[2]));
+    }
+
+    #[test]
+    fn shortcuts_toggle_follower_borders_pause_and_quit() {
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), shortcuts_config()).unwrap();
+
+        tap_shortcut(&mut app, &platform, Key::VK_T);
+        assert!(has_borders(&platform, hwnds[0]));
+        assert!(!has_borders(&platform, hwnds[2]));
+        assert!(!has_borders(&platform, hwnds[3]));
+        tap_shortcut(&mut app, &platform, Key::VK_T);
+        assert!(has_borders(&platform, hwnds[2]));
+        assert!(has_borders(&platform, hwnds[3]));
+
+        tap_shortcut(&mut app, &platform, Key::VK_SPACE);
+        assert!(app.is_paused());
+        tap_shortcut(&mut app, &platform, Key::VK_SPACE);
+        assert!(!app.is_paused());
+
+        assert!(!app.quit_requested());
+        assert!(tap_shortcut(&mut app, &platform, Key::VK_Q));
+        assert!(app.quit_requested());
+    }
+
+    #[test]
+    fn reload_config_recompiles_the_matcher() {
+        let path =
+            std::env::temp_dir().join(format!("is_boxer_like_reload_{}.ron", std::process::id()));
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), shortcuts_config()).unwrap();
+        app.set_config_path(&path);
+
+        std::fs::write(
+            &path,
+            "(window_name: \"discord\", state_file: None, slots: (file: None), input: Poll, mode: Bot)",
+        )
+        .unwrap();
+        assert!(tap_shortcut(&mut app, &platform, Key::VK_C));
+        assert!(app.has_hwnd(hwnds[1]));
+        assert!(!app.has_hwnd(hwnds[0]));
+        assert!(matches!(app.config.mode, Mode::Bot));
+
+        // A broken file leaves everything as it was
+        std::fs::write(&path, "(window_name: ").unwrap();
+        assert!(matches!(app.reload_config(), Err(Error::Config(_))));
+        assert!(app.has_hwnd(hwnds[1]));
+        std::fs::write(
+            &path,
+            "(window_name: (title: \"(\"), state_file: None, slots: (file: None), input: Poll, mode: Bot)",
+        )
+        .unwrap();
+        assert!(matches!(
+            app.reload_config(),
+            Err(Error::InvalidWindowName(_))
+        ));
+        assert!(app.has_hwnd(hwnds[1]));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn mimic_ignores_unknown_foreground() {
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), config()).unwrap();
+
+        platform.set_foreground_window(hwnds[1]);
+        platform.press(Key::VK_1);
+        app.mimic();
+
+        app.flush();
+        assert!(platform.posted().is_empty());
+    }
+
+    #[test]
+    fn layout_windows_strips_borders() {
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), config()).unwrap();
+        app.layout_windows();
+
+        let main = platform.window_rect(hwnds[0]);
+        assert_eq!((main.x, main.y), (0, 0));
+        assert_eq!(main.width, 1920);
+
+        let style = platform.window_style(hwnds[2]);
+        assert_eq!(style.style & WS_CAPTION.0 as isize, 0);
+        let style = platform.window_style(hwnds[1]);
+        assert_ne!(style.style & WS_CAPTION.0 as isize, 0);
+    }
+
+    #[test]
+    fn layout_windows_uses_monitor_offsets() {
+        let (platform, hwnds) = setup();
+        platform.set_monitors(vec![rect(0, 0, 1920, 1040), rect(1920, 100, 2560, 1400)]);
+
+        let config = Config {
+            layout_monitors: LayoutMonitors {
+                main: 1,
+                followers: Some(0),
+            },
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+        app.layout_windows();
+
+        assert_eq!(platform.window_rect(hwnds[0]), rect(1920, 100, 2560, 1400));
+        assert_eq!(platform.window_rect(hwnds[2]), rect(0, 0, 960, 1040));
+        assert_eq!(platform.window_rect(hwnds[3]), rect(960, 0, 960, 1040));
+    }
+
+    #[test]
+    fn restore_windows_undoes_layout() {
+        let (platform, hwnds) = setup();
+        let before = hwnds
+            .iter()
+            .map(|hwnd| (platform.window_rect(*hwnd), platform.window_style(*hwnd)))
+            .collect::<Vec<_>>();
+
+        let mut app = App::new(platform.clone(), config()).unwrap();
+        app.layout_windows();
+        assert_ne!(platform.window_rect(hwnds[0]), before[0].0);
+
+        app.restore_windows();
+        for (hwnd, (rect, style)) in hwnds.iter().zip(before) {
+            assert_eq!(platform.window_rect(*hwnd), rect);
+            assert_eq!(platform.window_style(*hwnd), style);
+        }
+    }
+
+    #[test]
+    fn shutdown_leaves_untouched_windows_alone() {
+        let (platform, hwnds) = setup();
+        let config = Config {
+            layout: LayoutOptions::Never,
+            ..config()
+        };
+        let before = platform.window_rect(hwnds[2]);
+
+        let mut app = App::new(platform.clone(), config).unwrap();
+        // Placed by hand while the app runs
+        platform.move_window(hwnds[0], rect(100, 100, 640, 480), false);
+        app.toggle_borders();
+        assert!(!has_borders(&platform, hwnds[2]));
+
+        app.shutdown();
+        assert_eq!(platform.window_rect(hwnds[0]), rect(100, 100, 640, 480));
+        assert_eq!(platform.window_rect(hwnds[2]), before);
+        assert!(has_borders(&platform, hwnds[2]));
+    }
+
+    #[test]
+    fn restore_saved_after_crash() {
+        let path =
+            std::env::temp_dir().join(format!("is_boxer_like_test_{}.ron", std::process::id()));
+        let (platform, hwnds) = setup();
+        let before = platform.window_rect(hwnds[2]);
+
+        let mut app = App::new(
+            platform.clone(),
+            Config {
+                state_file: Some(path.clone()),
+                ..config()
+            },
+        )
+        .unwrap();
+        app.layout_windows();
+        // Crashing: nothing puts the windows back
+        drop(app);
+
+        platform.remove_window(hwnds[3]);
+        assert_eq!(restore::restore_saved(&platform, &path).unwrap(), 2);
+        assert_eq!(platform.window_rect(hwnds[2]), before);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn restore_saved_skips_reused_handles() {
+        let path =
+            std::env::temp_dir().join(format!("is_boxer_like_reused_{}.ron", std::process::id()));
+        let (platform, hwnds) = setup();
+
+        let mut app = App::new(
+            platform.clone(),
+            Config {
+                state_file: Some(path.clone()),
+                ..config()
+            },
+        )
+        .unwrap();
+        app.layout_windows();
+        let laid_out = hwnds
+            .iter()
+            .map(|hwnd| platform.window_rect(*hwnd))
+            .collect::<Vec<_>>();
+        drop(app);
+
+        // After a reboot, the handles went to other processes, or to a new
+        // process that got the same pid
+        platform.set_window_process(hwnds[2], 4242, "notepad.exe");
+        let pid = platform.window_process_id(hwnds[3]);
+        platform.set_process_start_time(pid, 1);
+
+        assert_eq!(restore::restore_saved(&platform, &path).unwrap(), 1);
+        assert_ne!(platform.window_rect(hwnds[0]), laid_out[0]);
+        assert_eq!(platform.window_rect(hwnds[2]), laid_out[2]);
+        assert_eq!(platform.window_rect(hwnds[3]), laid_out[3]);
+    }
+
+    #[test]
+    fn no_matching_windows_is_an_error() {
+        let platform = FakePlatform::new();
+        platform.add_window("Discord", rect(0, 0, 800, 600));
+
+        let error = App::new(platform.clone(), config()).err();
+        assert_eq!(
+            error,
+            Some(Error::NoMatchingWindows {
+                pattern: "warcraft".into()
+            })
+        );
+
+        let empty = Config {
+            window_name: WindowName::from(""),
+            ..config()
+        };
+        assert_eq!(
+            App::new(platform, empty).err(),
+            Some(Error::EmptyWindowName)
+        );
+    }
+
+    #[test]
+    fn wait_for_windows_until_enough_appear() {
+        let platform = FakePlatform::new();
+        let wait = WaitOptions {
+            count: 2,
+            timeout: Some(config::Duration::Seconds(5)),
+            poll: config::Duration::Milliseconds(1),
+        };
+
+        let launcher = platform.clone();
+        let handle = std::thread::spawn(move || {
+            for _ in 0..2 {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                launcher.add_window("World of Warcraft", rect(0, 0, 800, 600));
+            }
+        });
+
+        let config = Config {
+            wait: Some(wait),
+            ..config()
+        };
+        let app = App::new(platform, config).unwrap();
+        handle.join().unwrap();
+
+        assert_eq!(app.windows.len(), 2);
+    }
+
+    #[test]
+    fn wait_for_windows_stops_on_quit() {
+        let (platform, _) = setup();
+        let config = Config {
+            wait: Some(WaitOptions {
+                count: 4,
+                timeout: None,
+                poll: config::Duration::Milliseconds(1),
+            }),
+            ..config()
+        };
+
+        let error = App::new_until(platform, config, &|| true).err();
+        assert_eq!(error, Some(Error::Interrupted));
+    }
+
+    #[test]
+    fn wait_for_windows_times_out() {
+        let (platform, _) = setup();
+        let config = Config {
+            wait: Some(WaitOptions {
+                count: 4,
+                timeout: Some(config::Duration::Milliseconds(20)),
+                poll: config::Duration::Milliseconds(1),
+            }),
+            ..config()
+        };
+
+        let error = App::new(platform, config).err();
+        assert_eq!(
+            error,
+            Some(Error::Timeout {
+                pattern: "warcraft".into(),
+                found: 3,
+                wanted: 4
+            })
+        );
+    }
+
+    #[test]
+    fn refresh_windows_tracks_closed_and_opened_clients() {
+        let (platform, hwnds) = setup();
+        let mut app = App::new(platform.clone(), config()).unwrap();
+        let events = app.subscribe();
+
+        platform.remove_window(hwnds[0]);
+        let relaunched = platform.add_window("World of Warcraft", rect(0, 0, 800, 600));
+        let changes = app.refresh_windows();
+
+        assert_eq!(
+            changes,
+            vec![
+                WindowEvent::Removed {
+                    hwnd: hwnds[0],
+                    title: "World of Warcraft".into()
+                },
+                WindowEvent::Added {
+                    hwnd: relaunched,
+                    title: "World of Warcraft".into()
+                },
+                // The new client takes the free slot 0, so it leads
+                WindowEvent::MainChanged {
+                    hwnd: Some(relaunched)
+                },
+            ]
+        );
+        assert_eq!(events.try_iter().collect::<Vec<_>>(), changes);
+        assert!(app.refresh_windows().is_empty());
+
+        // Nothing is sent to the closed window anymore
+        platform.set_foreground_window(hwnds[2]);
+        platform.press(Key::VK_1);
+        app.mimic();
+        app.flush();
+        let targets = platform
+            .take_posted()
+            .into_iter()
+            .map(|m| m.hwnd)
+            .collect::<Vec<_>>();
+        assert_eq!(targets, vec![relaunched, hwnds[3]]);
+    }
+
+    #[test]
+    fn slots_survive_restarts() {
+        let path =
+            std::env::temp_dir().join(format!("is_boxer_like_slots_{}.ron", std::process::id()));
+        let platform = FakePlatform::new();
+        let tank = platform.add_window("World of Warcraft - Tank", rect(0, 0, 800, 600));
+        let mage = platform.add_window("World of Warcraft - Mage", rect(0, 0, 800, 600));
+        let healer = platform.add_window("World of Warcraft - Healer", rect(0, 0, 800, 600));
+
+        let config = Config {
+            slots: SlotOptions {
+                rules: vec![SlotRule {
+                    title: "healer".into(),
+                    slot: 9,
+                }],
+                order: SlotOrder::Enumeration,
+                file: Some(path.clone()),
+            },
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config.clone()).unwrap();
+        app.layout_windows();
+
+        // The slots in between are empty, the healer still gets the third cell of three
+        let expected = layout::layout(
+            &LayoutStrategy::default(),
+            &platform.monitors(),
+            LayoutMonitors::default(),
+            3,
+            0,
+        );
+        assert_eq!(Some(platform.window_rect(healer)), expected[2]);
+        drop(app);
+
+        // The tank is relaunched, and now listed after the others
+        platform.remove_window(tank);
+        let tank = platform.add_window("World of Warcraft - Tank", rect(0, 0, 800, 600));
+
+        let app = App::new(platform, config).unwrap();
+        let slots = app
+            .windows
+            .iter()
+            .map(|w| (w.hwnd, w.slot))
+            .collect::<Vec<_>>();
+        assert_eq!(slots, vec![(tank, 0), (mage, 1), (healer, 9)]);
+        assert_eq!(app.main_hwnd, Some(tank));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn refresh_windows_lays_out_again() {
+        let (platform, hwnds) = setup();
+        let config = Config {
+            layout: LayoutOptions::Always,
+            ..config()
+        };
+        let mut app = App::new(platform.clone(), config).unwrap();
+        app.layout_windows();
+
+        platform.remove_window(hwnds[3]);
+        let changes = app.refresh_windows();
+
+        assert_eq!(changes.last(), Some(&WindowEvent::LaidOut));
+        assert_eq!(platform.window_rect(hwnds[2]), rect(0, 520, 1920, 520));
     }
 }
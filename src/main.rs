@@ -1,14 +1,13 @@
-mod config;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
-use is_boxer_like::App;
 use is_boxer_like::BotAction;
+use is_boxer_like::Config;
 use is_boxer_like::LayoutOptions::{Always, Init};
 use is_boxer_like::Mode::{Bot, Mimic};
-use is_boxer_like::WPARAM;
-use is_boxer_like::{Config, Mode};
+use is_boxer_like::{restore, App, Error, NativePlatform, Platform};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
@@ -17,25 +16,62 @@ struct Args {
     /// Number of times to greet
     #[arg(short, long)]
     config: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Puts back the windows left behind by a run that did not exit cleanly
+    Restore,
 }
 
-fn bot_loop(app: &mut App) {
+/// Set by the Ctrl+C handler, the loops return as soon as they see it
+static QUIT: AtomicBool = AtomicBool::new(false);
+
+fn should_quit() -> bool {
+    QUIT.load(Ordering::SeqCst)
+}
+
+fn track_windows<P: Platform>(app: &mut App<P>) {
+    for event in app.poll_windows() {
+        println!("{}", event);
+    }
+}
+
+/// Lag from which the followers are reported to be falling behind
+const LAG_WARNING: Duration = Duration::from_millis(100);
+
+/// Prints the queue depth and lag whenever the worst lag gets worse
+fn report_dispatch<P: Platform>(app: &App<P>, worst: &mut Duration) {
+    let stats = app.dispatch_stats();
+    if stats.max_lag > *worst && stats.max_lag >= LAG_WARNING {
+        println!(
+            "Followers falling behind: {} messages queued, {:?} late",
+            stats.queued, stats.lag
+        );
+    }
+    *worst = stats.max_lag;
+}
+
+fn bot_loop<P: Platform>(app: &mut App<P>) {
     let mut last_sleep = None;
 
     let mut i = 0;
     loop {
-        if app.global_shortcuts() {
+        if app.global_shortcuts() || should_quit() {
             return;
         }
+        track_windows(app);
 
-        let actions = &app.config.bot_action;
-        let current_action = &actions[i];
+        let current_action = app.config.bot_action[i].clone();
 
         match current_action {
             BotAction::Sleep(duration) => {
                 if last_sleep.is_none() {
                     last_sleep = Some(std::time::Instant::now());
-                } else if last_sleep.unwrap().elapsed() >= (*duration).into() {
+                } else if last_sleep.unwrap().elapsed() >= duration.into() {
                     last_sleep = None;
                 }
 
@@ -43,18 +79,20 @@ fn bot_loop(app: &mut App) {
             }
             BotAction::MouseTo(_, _) => {}
             BotAction::KeyStroke(key) => {
-                app.send_key_up(WPARAM(*key as usize));
-                app.send_key_down(WPARAM(*key as usize));
+                app.send_key_up(key);
+                app.send_key_down(key);
+                // Paces the bot on the followers, instead of queueing ahead of them
+                app.flush();
             }
         }
 
         if last_sleep.is_none() {
-            i = (i + 1) % actions.len();
+            i = (i + 1) % app.config.bot_action.len();
         }
     }
 }
 
-fn mimic_loop(app: &mut App) {
+fn mimic_loop<P: Platform>(app: &mut App<P>) {
     let Config {
         mimic_timer: delay,
         layout,
@@ -62,18 +100,24 @@ fn mimic_loop(app: &mut App) {
     } = app.config.clone();
 
     let delay: Duration = delay.into();
+    let mut worst_lag = Duration::ZERO;
+    let mut paused = app.is_paused();
     loop {
-        if matches!(layout, Always) {
-            if app.swap_windows() {
-                app.foreground();
-            }
+        if matches!(layout, Always) && app.swap_windows() {
+            app.foreground();
         }
 
-        if app.global_shortcuts() {
+        if app.global_shortcuts() || should_quit() {
             return;
         }
+        track_windows(app);
 
         app.mimic();
+        if app.is_paused() != paused {
+            paused = app.is_paused();
+            println!("Broadcast {}", if paused { "paused" } else { "resumed" });
+        }
+        report_dispatch(app, &mut worst_lag);
         std::thread::sleep(delay);
     }
 }
@@ -86,7 +130,8 @@ fn main() -> Result<(), eframe::Error> {
 
     // return Ok(());
 
-    let Args { config } = Args::parse();
+    let Args { config, command } = Args::parse();
+    let config_path = config.clone();
     if let Some(config_path) = &config {
         println!("Using config file: {}", config_path);
     } else {
@@ -99,24 +144,57 @@ fn main() -> Result<(), eframe::Error> {
         Config::default()
     };
 
-    if config.window_name.is_empty() {
-        panic!("No window name specified");
+    if let Some(Command::Restore) = command {
+        let Some(path) = &config.state_file else {
+            println!("No state file configured, nothing to restore");
+            return Ok(());
+        };
+
+        match restore::restore_saved(&NativePlatform::default(), path) {
+            Ok(count) => println!("Restored {} windows", count),
+            Err(e) => println!("Nothing to restore from {}: {}", path.display(), e),
+        }
+        return Ok(());
+    }
+
+    // Installed before waiting for the windows, which Ctrl+C stops. Held keys
+    // are released by `shutdown`, or by dropping the app on a panic.
+    ctrlc::set_handler(|| QUIT.store(true, Ordering::SeqCst))
+        .expect("Could not set the Ctrl+C handler");
+
+    if config.wait.is_some() {
+        println!("Waiting for \"{}\" windows", config.window_name);
     }
 
-    let mut app = App::new(config);
+    let mut app = match App::new_until(NativePlatform::default(), config, &should_quit) {
+        Ok(app) => app,
+        Err(Error::Interrupted) => return Ok(()),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(path) = config_path {
+        app.set_config_path(path);
+    }
 
     if matches!(&app.config.layout, Init | Always) {
         app.layout_windows();
         app.foreground();
     }
 
-    loop {
+    while !should_quit() && !app.quit_requested() {
         if let Bot = app.config.mode {
             bot_loop(&mut app);
         } else if let Mimic = app.config.mode {
             mimic_loop(&mut app);
         } else {
             app.global_shortcuts();
+            track_windows(&mut app);
         }
     }
+
+    app.shutdown();
+    Ok(())
 }
@@ -0,0 +1,295 @@
+//! Decides which windows are game clients.
+
+use std::fmt;
+
+use regex::{Regex, RegexBuilder};
+use windows::HWND;
+
+use crate::platform::Platform;
+
+/// What the matcher knows about a window
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowInfo {
+    pub hwnd: HWND,
+    pub title: String,
+    pub class: String,
+    /// File name of the executable, without its directory
+    pub executable: String,
+    pub pid: u32,
+    pub visible: bool,
+    /// Not owned by another window (dialogs and tool windows are owned)
+    pub top_level: bool,
+}
+
+impl WindowInfo {
+    pub fn query<P: Platform>(platform: &P, hwnd: HWND) -> Self {
+        let pid = platform.window_process_id(hwnd);
+
+        Self {
+            hwnd,
+            title: platform.window_title(hwnd),
+            class: platform.window_class(hwnd),
+            executable: platform.process_executable(pid).unwrap_or_default(),
+            pid,
+            visible: platform.is_window_visible(hwnd),
+            top_level: platform.window_owner(hwnd).is_none(),
+        }
+    }
+}
+
+/// `window_name` in the config: either a plain title fragment, or a full spec
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum WindowName {
+    /// Case-insensitive substring of the title
+    Contains(String),
+    Spec(MatcherSpec),
+}
+
+/// Every field that is set must match. Regexes are case-insensitive.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct MatcherSpec {
+    /// Regex on the title
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Exact executable name, e.g. `"Wow.exe"` (case-insensitive)
+    #[serde(default)]
+    pub executable: Option<String>,
+    /// Exact window class
+    #[serde(default)]
+    pub class: Option<String>,
+    #[serde(default)]
+    pub visible_only: bool,
+    #[serde(default)]
+    pub top_level_only: bool,
+    /// Regexes on the title, a window matching any of them is ignored
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Ignores the windows of this very program
+    #[serde(default = "default_exclude_self")]
+    pub exclude_self: bool,
+}
+
+fn default_exclude_self() -> bool {
+    true
+}
+
+impl Default for MatcherSpec {
+    fn default() -> Self {
+        MatcherSpec {
+            title: None,
+            executable: None,
+            class: None,
+            visible_only: false,
+            top_level_only: false,
+            exclude: Vec::new(),
+            exclude_self: default_exclude_self(),
+        }
+    }
+}
+
+impl WindowName {
+    /// True when nothing would narrow the match down
+    pub fn is_empty(&self) -> bool {
+        match self {
+            WindowName::Contains(name) => name.is_empty(),
+            WindowName::Spec(spec) => {
+                spec.title.as_deref().is_none_or(str::is_empty)
+                    && spec.executable.is_none()
+                    && spec.class.is_none()
+            }
+        }
+    }
+
+    pub fn spec(&self) -> MatcherSpec {
+        match self {
+            WindowName::Contains(name) => MatcherSpec {
+                title: Some(regex::escape(name)),
+                ..MatcherSpec::default()
+            },
+            WindowName::Spec(spec) => spec.clone(),
+        }
+    }
+}
+
+impl From<&str> for WindowName {
+    fn from(name: &str) -> Self {
+        WindowName::Contains(name.to_string())
+    }
+}
+
+impl fmt::Display for WindowName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowName::Contains(name) => write!(f, "{}", name),
+            WindowName::Spec(spec) => {
+                let mut parts = Vec::new();
+                if let Some(title) = &spec.title {
+                    parts.push(format!("title ~ /{}/", title));
+                }
+                if let Some(executable) = &spec.executable {
+                    parts.push(format!("executable = {}", executable));
+                }
+                if let Some(class) = &spec.class {
+                    parts.push(format!("class = {}", class));
+                }
+                write!(f, "{}", parts.join(", "))
+            }
+        }
+    }
+}
+
+/// A `MatcherSpec` with its regexes compiled
+#[derive(Debug, Clone)]
+pub struct WindowMatcher {
+    spec: MatcherSpec,
+    title: Option<Regex>,
+    exclude: Vec<Regex>,
+    own_pid: u32,
+}
+
+fn regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+impl WindowMatcher {
+    pub fn new(name: &WindowName) -> Result<Self, regex::Error> {
+        let spec = name.spec();
+        Ok(WindowMatcher {
+            title: spec.title.as_deref().map(regex).transpose()?,
+            exclude: spec
+                .exclude
+                .iter()
+                .map(|pattern| regex(pattern))
+                .collect::<Result<_, _>>()?,
+            own_pid: std::process::id(),
+            spec,
+        })
+    }
+
+    pub fn matches(&self, info: &WindowInfo) -> bool {
+        let spec = &self.spec;
+
+        if spec.exclude_self && info.pid == self.own_pid {
+            return false;
+        }
+        if (spec.visible_only && !info.visible) || (spec.top_level_only && !info.top_level) {
+            return false;
+        }
+        if let Some(executable) = &spec.executable {
+            if !executable.eq_ignore_ascii_case(&info.executable) {
+                return false;
+            }
+        }
+        if let Some(class) = &spec.class {
+            if class != &info.class {
+                return false;
+            }
+        }
+        if let Some(title) = &self.title {
+            if !title.is_match(&info.title) {
+                return false;
+            }
+        }
+
+        !self.exclude.iter().any(|regex| regex.is_match(&info.title))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(title: &str, executable: &str) -> WindowInfo {
+        WindowInfo {
+            hwnd: HWND(1),
+            title: title.into(),
+            class: "GxWindowClass".into(),
+            executable: executable.into(),
+            pid: 1,
+            visible: true,
+            top_level: true,
+        }
+    }
+
+    #[test]
+    fn plain_name_is_a_case_insensitive_substring() {
+        let matcher = WindowMatcher::new(&"warcraft (".into()).unwrap();
+
+        assert!(matcher.matches(&window("World of Warcraft (Tank)", "Wow.exe")));
+        assert!(!matcher.matches(&window("World of Warcraft", "Wow.exe")));
+    }
+
+    #[test]
+    fn spec_from_ron() {
+        let name: WindowName = ron::from_str(
+            r#"(
+                title: "^World of Warcraft",
+                executable: "wow.exe",
+                class: "GxWindowClass",
+                visible_only: true,
+                exclude: ["discord"],
+            )"#,
+        )
+        .unwrap();
+        let matcher = WindowMatcher::new(&name).unwrap();
+
+        assert!(matcher.matches(&window("World of Warcraft", "Wow.exe")));
+        assert!(!matcher.matches(&window("World of Warcraft", "chrome.exe")));
+        assert!(!matcher.matches(&window("World of Warcraft - Discord", "Wow.exe")));
+
+        let hidden = WindowInfo {
+            visible: false,
+            ..window("World of Warcraft", "Wow.exe")
+        };
+        assert!(!matcher.matches(&hidden));
+
+        let other_class = WindowInfo {
+            class: "Chrome_WidgetWin_1".into(),
+            ..window("World of Warcraft", "Wow.exe")
+        };
+        assert!(!matcher.matches(&other_class));
+    }
+
+    #[test]
+    fn exclusions_and_own_windows() {
+        let name = WindowName::Spec(MatcherSpec {
+            title: Some("warcraft".into()),
+            top_level_only: true,
+            exclude: vec!["- youtube".into(), "wowhead".into()],
+            ..MatcherSpec::default()
+        });
+        let matcher = WindowMatcher::new(&name).unwrap();
+
+        assert!(!matcher.matches(&window("Warcraft lore - YouTube", "firefox.exe")));
+        assert!(!matcher.matches(&window("Wowhead: World of Warcraft", "firefox.exe")));
+
+        let own = WindowInfo {
+            pid: std::process::id(),
+            ..window("World of Warcraft are being mimic-ed", "is_boxer_like.exe")
+        };
+        assert!(!matcher.matches(&own));
+
+        let owned = WindowInfo {
+            top_level: false,
+            ..window("World of Warcraft", "Wow.exe")
+        };
+        assert!(!matcher.matches(&owned));
+    }
+
+    #[test]
+    fn empty_names() {
+        assert!(WindowName::from("").is_empty());
+        assert!(WindowName::Spec(MatcherSpec::default()).is_empty());
+        assert!(!WindowName::Spec(MatcherSpec {
+            executable: Some("Wow.exe".into()),
+            ..MatcherSpec::default()
+        })
+        .is_empty());
+        assert!(WindowMatcher::new(&WindowName::Spec(MatcherSpec {
+            title: Some("(".into()),
+            ..MatcherSpec::default()
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,275 @@
+//! Mirrors the leader's clicks, drags and wheel on the followers, at the same
+//! place relative to each client area.
+
+use windows::messaging::{
+    WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEMOVE, WM_MOUSEWHEEL,
+    WM_RBUTTONDOWN, WM_RBUTTONUP,
+};
+use windows::vk::{MK_CONTROL, MK_LBUTTON, MK_MBUTTON, MK_RBUTTON, MK_SHIFT};
+use windows::{LPARAM, WPARAM};
+
+use crate::chord::Modifiers;
+use crate::config::Key;
+use crate::geometry::Rect;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+impl MouseButton {
+    pub const ALL: [MouseButton; 3] = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+    /// Virtual key of the button, for its state
+    pub fn key(self) -> Key {
+        match self {
+            MouseButton::Left => Key::VK_LBUTTON,
+            MouseButton::Right => Key::VK_RBUTTON,
+            MouseButton::Middle => Key::VK_MBUTTON,
+        }
+    }
+
+    /// `MK_*` flag of the button in the `wParam` of mouse messages
+    fn flag(self) -> usize {
+        match self {
+            MouseButton::Left => MK_LBUTTON.0 as usize,
+            MouseButton::Right => MK_RBUTTON.0 as usize,
+            MouseButton::Middle => MK_MBUTTON.0 as usize,
+        }
+    }
+
+    fn message(self, down: bool) -> u32 {
+        match (self, down) {
+            (MouseButton::Left, true) => WM_LBUTTONDOWN,
+            (MouseButton::Left, false) => WM_LBUTTONUP,
+            (MouseButton::Right, true) => WM_RBUTTONDOWN,
+            (MouseButton::Right, false) => WM_RBUTTONUP,
+            (MouseButton::Middle, true) => WM_MBUTTONDOWN,
+            (MouseButton::Middle, false) => WM_MBUTTONUP,
+        }
+    }
+}
+
+/// Something the leader did with the mouse, at a point in screen coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEvent {
+    Button {
+        button: MouseButton,
+        down: bool,
+        x: i32,
+        y: i32,
+    },
+    Move {
+        x: i32,
+        y: i32,
+    },
+    /// `delta` is a multiple of 120 (`WHEEL_DELTA`) per notch, positive away from the user
+    Wheel {
+        delta: i16,
+        x: i32,
+        y: i32,
+    },
+}
+
+impl MouseEvent {
+    pub fn position(&self) -> (i32, i32) {
+        match *self {
+            MouseEvent::Button { x, y, .. }
+            | MouseEvent::Move { x, y }
+            | MouseEvent::Wheel { x, y, .. } => (x, y),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MouseMapping {
+    /// Same fraction of the width and of the height, whatever the aspect ratios
+    #[default]
+    Normalized,
+    /// One scale for both axes, keeping the leader's aspect ratio, centred in the follower
+    Scaled,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MouseOptions {
+    /// Mirrors the mouse at all
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub mapping: MouseMapping,
+}
+
+/// Point of the `to` client area matching `point` in the `from` client area,
+/// both relative to the top-left corner of their area
+pub fn map_point(mapping: MouseMapping, from: Rect, to: Rect, point: (i32, i32)) -> (i32, i32) {
+    if from.width <= 0 || from.height <= 0 || to.width <= 0 || to.height <= 0 {
+        return (0, 0);
+    }
+
+    let (scale_x, scale_y, offset_x, offset_y) = match mapping {
+        MouseMapping::Normalized => (
+            to.width as f64 / from.width as f64,
+            to.height as f64 / from.height as f64,
+            0.0,
+            0.0,
+        ),
+        MouseMapping::Scaled => {
+            let scale = f64::min(
+                to.width as f64 / from.width as f64,
+                to.height as f64 / from.height as f64,
+            );
+            (
+                scale,
+                scale,
+                (to.width as f64 - from.width as f64 * scale) / 2.0,
+                (to.height as f64 - from.height as f64 * scale) / 2.0,
+            )
+        }
+    };
+
+    // From pixel centre to pixel centre, so the corners map onto the corners
+    let map = |value: i32, scale: f64, offset: f64, size: i32| {
+        let mapped = offset + (value as f64 + 0.5) * scale;
+        (mapped.floor() as i32).clamp(0, size - 1)
+    };
+
+    (
+        map(point.0, scale_x, offset_x, to.width),
+        map(point.1, scale_y, offset_y, to.height),
+    )
+}
+
+/// `lParam` of a mouse message, x in the low word and y in the high word
+pub fn point_lparam((x, y): (i32, i32)) -> LPARAM {
+    LPARAM((((y as u16 as u32) << 16) | x as u16 as u32) as isize)
+}
+
+/// Messages mirroring `event` on a follower, where it happens at `client`
+/// (client coordinates) and `screen` (screen coordinates). `buttons` are the
+/// buttons held once the event is over.
+pub fn messages(
+    event: &MouseEvent,
+    buttons: &[MouseButton],
+    modifiers: Modifiers,
+    client: (i32, i32),
+    screen: (i32, i32),
+) -> Vec<(u32, WPARAM, LPARAM)> {
+    let mut flags = buttons
+        .iter()
+        .fold(0, |flags, button| flags | button.flag());
+    if modifiers.shift {
+        flags |= MK_SHIFT.0 as usize;
+    }
+    if modifiers.ctrl {
+        flags |= MK_CONTROL.0 as usize;
+    }
+
+    match *event {
+        // The cursor is moved there first, games read it from the last move
+        MouseEvent::Button {
+            button, down: true, ..
+        } => vec![
+            (
+                WM_MOUSEMOVE,
+                WPARAM(flags & !button.flag()),
+                point_lparam(client),
+            ),
+            (button.message(true), WPARAM(flags), point_lparam(client)),
+        ],
+        MouseEvent::Button {
+            button,
+            down: false,
+            ..
+        } => vec![(button.message(false), WPARAM(flags), point_lparam(client))],
+        MouseEvent::Move { .. } => vec![(WM_MOUSEMOVE, WPARAM(flags), point_lparam(client))],
+        // The wheel is the only one in screen coordinates
+        MouseEvent::Wheel { delta, .. } => vec![(
+            WM_MOUSEWHEEL,
+            WPARAM(((delta as u16 as usize) << 16) | flags),
+            point_lparam(screen),
+        )],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEADER: Rect = Rect::new(0, 0, 2000, 1000);
+
+    #[test]
+    fn normalized_keeps_fractions_of_each_axis() {
+        let follower = Rect::new(0, 0, 400, 400);
+        let map = |point| map_point(MouseMapping::Normalized, LEADER, follower, point);
+
+        assert_eq!(map((0, 0)), (0, 0));
+        assert_eq!(map((1000, 500)), (200, 200));
+        assert_eq!(map((1999, 999)), (399, 399));
+        assert_eq!(map((500, 750)), (100, 300));
+    }
+
+    #[test]
+    fn scaled_keeps_the_aspect_ratio() {
+        // Half the size horizontally is the smaller scale, the picture is centred vertically
+        let follower = Rect::new(0, 0, 1000, 800);
+        let map = |point| map_point(MouseMapping::Scaled, LEADER, follower, point);
+
+        assert_eq!(map((0, 0)), (0, 150));
+        assert_eq!(map((1000, 500)), (500, 400));
+        assert_eq!(map((1999, 999)), (999, 649));
+    }
+
+    #[test]
+    fn points_outside_stay_in_the_follower() {
+        let follower = Rect::new(0, 0, 200, 100);
+
+        assert_eq!(
+            map_point(MouseMapping::Normalized, LEADER, follower, (-50, 5000)),
+            (0, 99)
+        );
+        assert_eq!(
+            map_point(MouseMapping::Scaled, LEADER, Rect::default(), (10, 10)),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn click_drag_and_wheel_messages() {
+        let press = MouseEvent::Button {
+            button: MouseButton::Right,
+            down: true,
+            x: 0,
+            y: 0,
+        };
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::NONE
+        };
+
+        assert_eq!(
+            messages(&press, &[MouseButton::Right], shift, (10, 20), (0, 0)),
+            vec![
+                (WM_MOUSEMOVE, WPARAM(0x04), LPARAM(0x0014_000A)),
+                (WM_RBUTTONDOWN, WPARAM(0x06), LPARAM(0x0014_000A)),
+            ]
+        );
+
+        let drag = MouseEvent::Move { x: 0, y: 0 };
+        assert_eq!(
+            messages(&drag, &[MouseButton::Left], Modifiers::NONE, (3, 4), (0, 0)),
+            vec![(WM_MOUSEMOVE, WPARAM(0x01), LPARAM(0x0004_0003))]
+        );
+
+        let wheel = MouseEvent::Wheel {
+            delta: -120,
+            x: 0,
+            y: 0,
+        };
+        assert_eq!(
+            messages(&wheel, &[], Modifiers::NONE, (3, 4), (1500, 20)),
+            vec![(WM_MOUSEWHEEL, WPARAM(0xFF88_0000), LPARAM(0x0014_05DC))]
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+use windows::{
+    styles::{
+        WS_CAPTION, WS_EX_CLIENTEDGE, WS_MAXIMIZEBOX, WS_MINIMIZEBOX, WS_SYSMENU, WS_THICKFRAME,
+    },
+    HWND, LPARAM, WPARAM,
+};
+
+use super::{Platform, WindowStyle};
+use crate::config::Key;
+use crate::geometry::Rect;
+
+/// A message that went through `Platform::post_message`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PostedMessage {
+    pub hwnd: HWND,
+    pub message: u32,
+    pub wparam: usize,
+    pub lparam: isize,
+    /// When it was posted
+    pub at: Instant,
+}
+
+#[derive(Debug, Clone)]
+struct FakeWindow {
+    hwnd: HWND,
+    title: String,
+    class: String,
+    pid: u32,
+    visible: bool,
+    owner: Option<HWND>,
+    rect: Rect,
+    style: WindowStyle,
+    level: Option<HWND>,
+}
+
+#[derive(Debug)]
+struct State {
+    windows: Vec<FakeWindow>,
+    next_hwnd: isize,
+    foreground: HWND,
+    keys: HashSet<Key>,
+    cursor: (i32, i32),
+    monitors: Vec<Rect>,
+    executables: HashMap<u32, String>,
+    start_times: HashMap<u32, u64>,
+    posted: Vec<PostedMessage>,
+}
+
+/// In-memory desktop, scripted by tests (or by `main` off Windows).
+///
+/// Clones share the same desktop, so a test can keep one around to inspect
+/// what the `App` did with the other.
+#[derive(Debug, Clone)]
+pub struct FakePlatform {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for FakePlatform {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                windows: Vec::new(),
+                next_hwnd: 1,
+                foreground: HWND(0),
+                keys: HashSet::new(),
+                cursor: (0, 0),
+                monitors: vec![Rect::new(0, 0, 1920, 1040)],
+                executables: HashMap::new(),
+                start_times: HashMap::new(),
+                posted: Vec::new(),
+            })),
+        }
+    }
+}
+
+impl FakePlatform {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Opens a new bordered window and returns its handle.
+    ///
+    /// Every window gets its own process, with no executable name until
+    /// `set_window_process` is called.
+    pub fn add_window<S: Into<String>>(&self, title: S, rect: Rect) -> HWND {
+        let mut state = self.state();
+        let hwnd = HWND(state.next_hwnd);
+        state.next_hwnd += 1;
+
+        state.windows.push(FakeWindow {
+            hwnd,
+            title: title.into(),
+            class: String::new(),
+            pid: FIRST_PID + hwnd.0 as u32,
+            visible: true,
+            owner: None,
+            rect,
+            style: default_style(),
+            level: None,
+        });
+
+        hwnd
+    }
+
+    pub fn remove_window(&self, hwnd: HWND) {
+        self.state().windows.retain(|w| w.hwnd != hwnd);
+    }
+
+    pub fn set_window_class<S: Into<String>>(&self, hwnd: HWND, class: S) {
+        let class = class.into();
+        self.with_window(hwnd, |w| w.class = class);
+    }
+
+    /// Moves the window to process `pid`, running `executable`
+    pub fn set_window_process<S: Into<String>>(&self, hwnd: HWND, pid: u32, executable: S) {
+        self.with_window(hwnd, |w| w.pid = pid);
+        self.state().executables.insert(pid, executable.into());
+    }
+
+    /// Processes without a start time report their pid instead, so they start in pid order
+    pub fn set_process_start_time(&self, pid: u32, time: u64) {
+        self.state().start_times.insert(pid, time);
+    }
+
+    pub fn set_window_visible(&self, hwnd: HWND, visible: bool) {
+        self.with_window(hwnd, |w| w.visible = visible);
+    }
+
+    pub fn set_window_owner(&self, hwnd: HWND, owner: Option<HWND>) {
+        self.with_window(hwnd, |w| w.owner = owner);
+    }
+
+    /// Replaces the monitor list, the primary monitor first
+    pub fn set_monitors(&self, monitors: Vec<Rect>) {
+        self.state().monitors = monitors;
+    }
+
+    pub fn press(&self, key: Key) {
+        self.state().keys.insert(key);
+    }
+
+    pub fn release(&self, key: Key) {
+        self.state().keys.remove(&key);
+    }
+
+    pub fn set_cursor_position(&self, x: i32, y: i32) {
+        self.state().cursor = (x, y);
+    }
+
+    /// Last z-order level requested through `set_window_level`
+    pub fn window_level(&self, hwnd: HWND) -> Option<HWND> {
+        self.state()
+            .windows
+            .iter()
+            .find(|w| w.hwnd == hwnd)
+            .and_then(|w| w.level)
+    }
+
+    /// Every message posted so far, oldest first
+    pub fn posted(&self) -> Vec<PostedMessage> {
+        self.state().posted.clone()
+    }
+
+    /// Same as `posted`, but clears the log
+    pub fn take_posted(&self) -> Vec<PostedMessage> {
+        std::mem::take(&mut self.state().posted)
+    }
+
+    fn with_window<F: FnOnce(&mut FakeWindow)>(&self, hwnd: HWND, f: F) {
+        if let Some(window) = self.state().windows.iter_mut().find(|w| w.hwnd == hwnd) {
+            f(window);
+        }
+    }
+
+    fn get_window<T, F: FnOnce(&FakeWindow) -> T>(&self, hwnd: HWND, f: F) -> Option<T> {
+        self.state().windows.iter().find(|w| w.hwnd == hwnd).map(f)
+    }
+}
+
+/// Keeps fake processes clear of small real pids, like our own
+const FIRST_PID: u32 = 100_000;
+
+fn default_style() -> WindowStyle {
+    WindowStyle {
+        style: (WS_CAPTION | WS_THICKFRAME | WS_MINIMIZEBOX | WS_MAXIMIZEBOX | WS_SYSMENU).0
+            as isize,
+        ex_style: WS_EX_CLIENTEDGE.0 as isize,
+    }
+}
+
+impl Platform for FakePlatform {
+    fn windows(&self) -> Vec<HWND> {
+        self.state().windows.iter().map(|w| w.hwnd).collect()
+    }
+
+    fn is_window(&self, hwnd: HWND) -> bool {
+        self.get_window(hwnd, |_| ()).is_some()
+    }
+
+    fn window_title(&self, hwnd: HWND) -> String {
+        self.get_window(hwnd, |w| w.title.clone())
+            .unwrap_or_default()
+    }
+
+    fn window_class(&self, hwnd: HWND) -> String {
+        self.get_window(hwnd, |w| w.class.clone())
+            .unwrap_or_default()
+    }
+
+    fn window_process_id(&self, hwnd: HWND) -> u32 {
+        self.get_window(hwnd, |w| w.pid).unwrap_or_default()
+    }
+
+    fn process_executable(&self, pid: u32) -> Option<String> {
+        self.state().executables.get(&pid).cloned()
+    }
+
+    fn process_start_time(&self, pid: u32) -> Option<u64> {
+        let time = self.state().start_times.get(&pid).copied();
+        Some(time.unwrap_or(pid as u64))
+    }
+
+    fn is_window_visible(&self, hwnd: HWND) -> bool {
+        self.get_window(hwnd, |w| w.visible).unwrap_or_default()
+    }
+
+    fn window_owner(&self, hwnd: HWND) -> Option<HWND> {
+        self.get_window(hwnd, |w| w.owner).flatten()
+    }
+
+    fn window_rect(&self, hwnd: HWND) -> Rect {
+        self.get_window(hwnd, |w| w.rect).unwrap_or_default()
+    }
+
+    /// Fake windows draw no borders, the client area is the whole window
+    fn client_rect(&self, hwnd: HWND) -> Rect {
+        self.window_rect(hwnd)
+    }
+
+    fn move_window(&self, hwnd: HWND, rect: Rect, _repaint: bool) {
+        self.with_window(hwnd, |w| w.rect = rect);
+    }
+
+    fn set_window_level(&self, hwnd: HWND, level: HWND) {
+        self.with_window(hwnd, |w| w.level = Some(level));
+    }
+
+    fn monitors(&self) -> Vec<Rect> {
+        self.state().monitors.clone()
+    }
+
+    fn window_style(&self, hwnd: HWND) -> WindowStyle {
+        self.get_window(hwnd, |w| w.style).unwrap_or_default()
+    }
+
+    fn set_window_style(&self, hwnd: HWND, style: WindowStyle) {
+        self.with_window(hwnd, |w| w.style = style);
+    }
+
+    fn foreground_window(&self) -> HWND {
+        self.state().foreground
+    }
+
+    fn set_foreground_window(&self, hwnd: HWND) {
+        self.state().foreground = hwnd;
+    }
+
+    fn is_key_down(&self, key: Key) -> bool {
+        self.state().keys.contains(&key)
+    }
+
+    fn cursor_position(&self) -> (i32, i32) {
+        self.state().cursor
+    }
+
+    fn post_message(&self, hwnd: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) {
+        self.state().posted.push(PostedMessage {
+            hwnd,
+            message,
+            wparam: wparam.0,
+            lparam: lparam.0,
+            at: Instant::now(),
+        });
+    }
+}
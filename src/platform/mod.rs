@@ -0,0 +1,72 @@
+use windows::{HWND, LPARAM, WPARAM};
+
+use crate::config::Key;
+use crate::geometry::Rect;
+use crate::input::InputSource;
+
+mod fake;
+#[cfg(windows)]
+mod win32;
+
+pub use fake::{FakePlatform, PostedMessage};
+#[cfg(windows)]
+pub use win32::Win32Platform;
+
+/// The platform `App` runs on when started from `main`
+#[cfg(windows)]
+pub type NativePlatform = Win32Platform;
+/// There is nothing to drive outside of Windows, the fake keeps the app runnable
+#[cfg(not(windows))]
+pub type NativePlatform = FakePlatform;
+
+/// `GWL_STYLE` and `GWL_EXSTYLE` bits of a window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WindowStyle {
+    pub style: isize,
+    pub ex_style: isize,
+}
+
+/// Everything `App` needs from the windowing system. Handles are cheap to
+/// clone, the dispatcher posts messages from a clone on its own thread.
+pub trait Platform: Clone + Send + 'static {
+    /// Top-level windows, in the order `EnumWindows` reports them
+    fn windows(&self) -> Vec<HWND>;
+    /// Whether the handle still points to an open window
+    fn is_window(&self, hwnd: HWND) -> bool;
+    fn window_title(&self, hwnd: HWND) -> String;
+    fn window_class(&self, hwnd: HWND) -> String;
+    fn window_process_id(&self, hwnd: HWND) -> u32;
+    /// File name of the executable running `pid`, `None` when it can't be queried
+    fn process_executable(&self, pid: u32) -> Option<String>;
+    /// When `pid` was started, in 100ns ticks since 1601 (a `FILETIME`)
+    fn process_start_time(&self, pid: u32) -> Option<u64>;
+    fn is_window_visible(&self, hwnd: HWND) -> bool;
+    /// Window owning this one, e.g. the main window of a dialog
+    fn window_owner(&self, hwnd: HWND) -> Option<HWND>;
+
+    /// Outer rect of the window, borders included
+    fn window_rect(&self, hwnd: HWND) -> Rect;
+    /// Client area of the window, in screen coordinates
+    fn client_rect(&self, hwnd: HWND) -> Rect;
+    fn move_window(&self, hwnd: HWND, rect: Rect, repaint: bool);
+    /// Changes the z-order of a window (`HWND_TOPMOST`, `HWND_NOTOPMOST`, ...)
+    fn set_window_level(&self, hwnd: HWND, level: HWND);
+    /// Work area of every monitor, the primary one first
+    fn monitors(&self) -> Vec<Rect>;
+
+    fn window_style(&self, hwnd: HWND) -> WindowStyle;
+    fn set_window_style(&self, hwnd: HWND, style: WindowStyle);
+
+    fn foreground_window(&self) -> HWND;
+    fn set_foreground_window(&self, hwnd: HWND);
+
+    fn is_key_down(&self, key: Key) -> bool;
+    /// Position of the cursor, in screen coordinates
+    fn cursor_position(&self) -> (i32, i32);
+    fn post_message(&self, hwnd: HWND, message: u32, wparam: WPARAM, lparam: LPARAM);
+
+    /// Source seeing the keys and the mouse as they are used, `None` when they can only be polled
+    fn input_hook(&self) -> Option<Box<dyn InputSource<Self>>> {
+        None
+    }
+}
@@ -0,0 +1,262 @@
+use windows::{
+    monitor::{
+        EnumDisplayMonitors, GetMonitorInfo, HDC, HMONITOR, MONITORINFO, MONITORINFOF_PRIMARY,
+    },
+    process::{
+        CloseHandle, EnumWindows, GetProcessTimes, OpenProcess, QueryFullProcessImageName,
+        PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    },
+    styles::{GWL_EXSTYLE, GWL_STYLE},
+    threading::GetWindowThreadProcessId,
+    vk::GetAsyncKeyState,
+    windowing::{
+        ClientToScreen, GetClassName, GetClientRect, GetCursorPos, GetForegroundWindow, GetWindow,
+        GetWindowLong, GetWindowRect, GetWindowText, GetWindowTextLength, IsWindow,
+        IsWindowVisible, MoveWindow, PostMessage, SetForegroundWindow, SetWindowLong, SetWindowPos,
+        GW_OWNER, SWP_FRAMECHANGED, SWP_NOMOVE, SWP_NOOWNERZORDER, SWP_NOSIZE, SWP_NOZORDER,
+        SWP_SHOWWINDOW,
+    },
+    BOOL, FILETIME, HWND, LPARAM, POINT, PSTR, RECT, WPARAM,
+};
+
+use super::{Platform, WindowStyle};
+use crate::config::Key;
+use crate::geometry::Rect;
+use crate::input::{InputHook, InputSource};
+
+/// Talks to the real desktop through the Win32 API
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Win32Platform;
+
+impl Platform for Win32Platform {
+    fn windows(&self) -> Vec<HWND> {
+        unsafe extern "system" fn callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+            let hwnds = &mut *(lparam.0 as *mut Vec<HWND>);
+            hwnds.push(hwnd);
+
+            // Return true to continue enumeration
+            BOOL::from(true)
+        }
+
+        let mut hwnds = Vec::new();
+        unsafe {
+            // SAFETY: we are passing the Vec as a pointer to the callback,
+            // which will be used to store the HWNDs. No other thread is using it.
+            EnumWindows(Some(callback), LPARAM(&mut hwnds as *mut _ as isize));
+        };
+
+        hwnds
+    }
+
+    fn is_window(&self, hwnd: HWND) -> bool {
+        unsafe { IsWindow(hwnd) }.as_bool()
+    }
+
+    fn window_title(&self, hwnd: HWND) -> String {
+        let name_length = unsafe { GetWindowTextLength(hwnd) };
+        let mut buffer = vec![0u8; name_length as usize + 1];
+        let copied = unsafe { GetWindowText(hwnd, &mut buffer) };
+        buffer.truncate(copied.max(0) as usize);
+
+        String::from_utf8_lossy(&buffer).to_string()
+    }
+
+    fn window_class(&self, hwnd: HWND) -> String {
+        // Class names are at most 256 characters long
+        let mut buffer = vec![0u8; 257];
+        let copied = unsafe { GetClassName(hwnd, &mut buffer) };
+        buffer.truncate(copied.max(0) as usize);
+
+        String::from_utf8_lossy(&buffer).to_string()
+    }
+
+    fn window_process_id(&self, hwnd: HWND) -> u32 {
+        let mut pid = 0;
+        unsafe {
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        }
+
+        pid
+    }
+
+    fn process_executable(&self, pid: u32) -> Option<String> {
+        let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
+
+        let mut buffer = vec![0u8; 1024];
+        let mut size = buffer.len() as u32;
+        let queried = unsafe {
+            let queried = QueryFullProcessImageName(
+                process,
+                PROCESS_NAME_WIN32,
+                PSTR(buffer.as_mut_ptr()),
+                &mut size,
+            );
+            CloseHandle(process);
+            queried
+        };
+        if !queried.as_bool() {
+            return None;
+        }
+        buffer.truncate(size as usize);
+
+        let path = String::from_utf8_lossy(&buffer).to_string();
+        path.rsplit('\\').next().map(str::to_string)
+    }
+
+    fn process_start_time(&self, pid: u32) -> Option<u64> {
+        let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
+
+        let mut created = FILETIME::default();
+        let (mut exited, mut kernel, mut user) = Default::default();
+        let queried = unsafe {
+            let queried =
+                GetProcessTimes(process, &mut created, &mut exited, &mut kernel, &mut user);
+            CloseHandle(process);
+            queried
+        };
+
+        queried
+            .as_bool()
+            .then_some(((created.dwHighDateTime as u64) << 32) | created.dwLowDateTime as u64)
+    }
+
+    fn is_window_visible(&self, hwnd: HWND) -> bool {
+        unsafe { IsWindowVisible(hwnd) }.as_bool()
+    }
+
+    fn window_owner(&self, hwnd: HWND) -> Option<HWND> {
+        let owner = unsafe { GetWindow(hwnd, GW_OWNER) };
+        (owner.0 != 0).then_some(owner)
+    }
+
+    fn window_rect(&self, hwnd: HWND) -> Rect {
+        let mut rect = RECT::default();
+        unsafe {
+            GetWindowRect(hwnd, &mut rect);
+        }
+
+        rect.into()
+    }
+
+    fn client_rect(&self, hwnd: HWND) -> Rect {
+        let mut rect = RECT::default();
+        // The client rect starts at 0, 0, its corner is moved on the screen
+        let mut corner = POINT::default();
+        unsafe {
+            GetClientRect(hwnd, &mut rect);
+            ClientToScreen(hwnd, &mut corner);
+        }
+
+        Rect::from(rect).translate(corner.x, corner.y)
+    }
+
+    fn move_window(&self, hwnd: HWND, rect: Rect, repaint: bool) {
+        unsafe {
+            MoveWindow(hwnd, rect.x, rect.y, rect.width, rect.height, repaint);
+        }
+    }
+
+    fn set_window_level(&self, hwnd: HWND, level: HWND) {
+        unsafe {
+            SetWindowPos(
+                hwnd,
+                level,
+                0,
+                0,
+                0,
+                0,
+                SWP_SHOWWINDOW | SWP_NOSIZE | SWP_NOMOVE,
+            );
+        }
+    }
+
+    fn monitors(&self) -> Vec<Rect> {
+        unsafe extern "system" fn callback(
+            hmonitor: HMONITOR,
+            _hdc: HDC,
+            _rect: *mut RECT,
+            lparam: LPARAM,
+        ) -> BOOL {
+            let monitors = &mut *(lparam.0 as *mut Vec<MONITORINFO>);
+
+            let mut info = MONITORINFO {
+                cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+                ..Default::default()
+            };
+            if GetMonitorInfo(hmonitor, &mut info).as_bool() {
+                monitors.push(info);
+            }
+
+            // Return true to continue enumeration
+            BOOL::from(true)
+        }
+
+        let mut monitors = Vec::<MONITORINFO>::new();
+        unsafe {
+            // SAFETY: same as `windows`, the Vec only lives for this call.
+            EnumDisplayMonitors(
+                HDC(0),
+                None,
+                Some(callback),
+                LPARAM(&mut monitors as *mut _ as isize),
+            );
+        };
+
+        // Stable sort, so the other monitors keep the system order
+        monitors.sort_by_key(|info| info.dwFlags & MONITORINFOF_PRIMARY == 0);
+        monitors
+            .into_iter()
+            .map(|info| info.rcWork.into())
+            .collect()
+    }
+
+    fn window_style(&self, hwnd: HWND) -> WindowStyle {
+        WindowStyle {
+            style: unsafe { GetWindowLong(hwnd, GWL_STYLE) },
+            ex_style: unsafe { GetWindowLong(hwnd, GWL_EXSTYLE) },
+        }
+    }
+
+    fn set_window_style(&self, hwnd: HWND, style: WindowStyle) {
+        let uflags = SWP_FRAMECHANGED | SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER | SWP_NOOWNERZORDER;
+        unsafe {
+            SetWindowLong(hwnd, GWL_STYLE, style.style);
+            SetWindowLong(hwnd, GWL_EXSTYLE, style.ex_style);
+            SetWindowPos(hwnd, HWND(0), 0, 0, 0, 0, uflags);
+        };
+    }
+
+    fn foreground_window(&self) -> HWND {
+        unsafe { GetForegroundWindow() }
+    }
+
+    fn set_foreground_window(&self, hwnd: HWND) {
+        unsafe {
+            SetForegroundWindow(hwnd);
+        }
+    }
+
+    fn is_key_down(&self, key: Key) -> bool {
+        let state = unsafe { GetAsyncKeyState(key as i32) } as u16;
+        state & 0x8000 != 0
+    }
+
+    fn cursor_position(&self) -> (i32, i32) {
+        let mut point = POINT::default();
+        unsafe {
+            GetCursorPos(&mut point);
+        }
+
+        (point.x, point.y)
+    }
+
+    fn post_message(&self, hwnd: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) {
+        unsafe {
+            PostMessage(hwnd, message, wparam, lparam);
+        };
+    }
+
+    fn input_hook(&self) -> Option<Box<dyn InputSource<Self>>> {
+        InputHook::install().map(|hook| Box::new(hook) as Box<dyn InputSource<Self>>)
+    }
+}
@@ -0,0 +1,167 @@
+//! Per-character overrides of the keys sent to a follower.
+//!
+//! Every follower starts from the global `keybind`, `skip_keybind` and
+//! `remap_keybind`, then each matching profile is applied in the order of the
+//! config, so a later profile wins over an earlier one. Within one layer,
+//! `keybind` adds keys, `skip_keybind` removes them, and `remap_keybind` wins
+//! over both.
+
+use std::collections::HashMap;
+
+use crate::chord::Chord;
+use crate::config::Config;
+use crate::groups;
+use crate::sequence::Remap;
+
+/// Keys of one follower: chord pressed on the leader -> what the window is sent
+pub type KeyMap = HashMap<Chord, Remap>;
+
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// Applies to windows whose title contains this (case-insensitive)
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Applies to the window in this slot
+    #[serde(default)]
+    pub slot: Option<usize>,
+    #[serde(default)]
+    pub keybind: Vec<Chord>,
+    #[serde(default)]
+    pub skip_keybind: Vec<Chord>,
+    #[serde(default)]
+    pub remap_keybind: HashMap<Chord, Remap>,
+}
+
+impl Profile {
+    /// Both `title` and `slot` must match when set, a profile with neither applies to everyone
+    pub fn matches(&self, title: &str, slot: usize) -> bool {
+        groups::selects(self.title.as_deref(), self.slot, title, slot)
+    }
+}
+
+fn apply(map: &mut KeyMap, keybind: &[Chord], skip: &[Chord], remap: &HashMap<Chord, Remap>) {
+    for key in keybind {
+        map.insert(*key, Remap::Chord(*key));
+    }
+    for key in skip {
+        map.remove(key);
+    }
+    map.extend(remap.iter().map(|(from, to)| (*from, to.clone())));
+}
+
+/// Keys sent to the follower with this title and slot
+pub fn key_map(config: &Config, title: &str, slot: usize) -> KeyMap {
+    let mut map = KeyMap::new();
+    apply(
+        &mut map,
+        &config.keybind,
+        &config.skip_keybind,
+        &config.remap_keybind,
+    );
+
+    for profile in config.profiles.iter().filter(|p| p.matches(title, slot)) {
+        apply(
+            &mut map,
+            &profile.keybind,
+            &profile.skip_keybind,
+            &profile.remap_keybind,
+        );
+    }
+
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Key::{self, *};
+
+    fn chords(keys: &[Key]) -> Vec<Chord> {
+        keys.iter().map(|key| Chord::from(*key)).collect()
+    }
+
+    fn remap(pairs: &[(Key, Key)]) -> HashMap<Chord, Remap> {
+        pairs
+            .iter()
+            .map(|(from, to)| (Chord::from(*from), Remap::from(*to)))
+            .collect()
+    }
+
+    fn sent(map: &KeyMap, key: Key) -> Option<Key> {
+        match map.get(&Chord::from(key))? {
+            Remap::Chord(chord) => Some(chord.key),
+            Remap::Sequence(_) => None,
+        }
+    }
+
+    fn config(profiles: Vec<Profile>) -> Config {
+        Config {
+            keybind: chords(&[VK_1, VK_2, VK_3, VK_LMENU]),
+            skip_keybind: chords(&[VK_3]),
+            remap_keybind: remap(&[(VK_LMENU, VK_SPACE)]),
+            profiles,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn global_keys_without_profiles() {
+        let map = key_map(&config(Vec::new()), "WoW - Tank", 0);
+
+        assert_eq!(
+            map,
+            remap(&[(VK_1, VK_1), (VK_2, VK_2), (VK_LMENU, VK_SPACE)])
+        );
+    }
+
+    #[test]
+    fn profiles_apply_to_matching_windows_only() {
+        let config = config(vec![
+            Profile {
+                title: Some("healer".into()),
+                remap_keybind: remap(&[(VK_1, VK_5)]),
+                skip_keybind: chords(&[VK_2]),
+                ..Profile::default()
+            },
+            Profile {
+                slot: Some(2),
+                keybind: chords(&[VK_3]),
+                ..Profile::default()
+            },
+        ]);
+
+        let tank = key_map(&config, "WoW - Tank", 1);
+        assert_eq!(sent(&tank, VK_1), Some(VK_1));
+        assert_eq!(sent(&tank, VK_3), None);
+
+        let healer = key_map(&config, "WoW - Healer", 2);
+        assert_eq!(sent(&healer, VK_1), Some(VK_5));
+        assert_eq!(sent(&healer, VK_2), None);
+        assert_eq!(sent(&healer, VK_3), Some(VK_3));
+    }
+
+    #[test]
+    fn later_profiles_win() {
+        let config = config(vec![
+            Profile {
+                remap_keybind: remap(&[(VK_1, VK_5)]),
+                ..Profile::default()
+            },
+            Profile {
+                title: Some("healer".into()),
+                slot: Some(3),
+                skip_keybind: chords(&[VK_1, VK_LMENU]),
+                ..Profile::default()
+            },
+        ]);
+
+        let healer = key_map(&config, "WoW - Healer", 3);
+        assert_eq!(sent(&healer, VK_1), None);
+        assert_eq!(sent(&healer, VK_LMENU), None);
+
+        // Same title, other slot: only the first profile applies
+        let other = key_map(&config, "WoW - Healer", 1);
+        assert_eq!(sent(&other, VK_1), Some(VK_5));
+        assert_eq!(sent(&other, VK_LMENU), Some(VK_SPACE));
+    }
+}
@@ -0,0 +1,98 @@
+//! How keys held on the leader repeat on the followers.
+//!
+//! The input sources report a held key again and again: at the keyboard's own
+//! rate with the hook, at the polling rate otherwise. A policy decides what
+//! the followers make of it.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config;
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Repeat {
+    /// Held keys are pressed once
+    None,
+    /// Like the system: a first repeat `delay` after the press, then one every `interval`
+    Timed {
+        delay: config::Duration,
+        interval: config::Duration,
+    },
+    /// Every repeat the input source reports is sent
+    #[default]
+    Passthrough,
+}
+
+impl Repeat {
+    /// Repeats due once a key is held for `held`, for a `Timed` policy. The
+    /// others never repeat on their own.
+    pub fn due(&self, held: Duration) -> u32 {
+        let Repeat::Timed { delay, interval } = *self else {
+            return 0;
+        };
+
+        let Some(repeating) = held.checked_sub(delay.into()) else {
+            return 0;
+        };
+        let interval = Duration::from(interval).max(Duration::from_millis(1));
+        let due = repeating.as_nanos() / interval.as_nanos() + 1;
+
+        due.min(u32::MAX as u128) as u32
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepeatOptions {
+    /// Policy of the windows without one of their own
+    #[serde(default)]
+    pub default: Repeat,
+    /// Policy of the window in a slot
+    #[serde(default)]
+    pub slots: HashMap<usize, Repeat>,
+}
+
+impl RepeatOptions {
+    /// Policy of the window in `slot`
+    pub fn policy(&self, slot: Option<usize>) -> Repeat {
+        slot.and_then(|slot| self.slots.get(&slot))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Duration::Milliseconds;
+
+    #[test]
+    fn timed_repeats_after_the_delay_at_the_interval() {
+        let timed = Repeat::Timed {
+            delay: Milliseconds(500),
+            interval: Milliseconds(30),
+        };
+        let due = |ms| timed.due(Duration::from_millis(ms));
+
+        assert_eq!(due(0), 0);
+        assert_eq!(due(499), 0);
+        assert_eq!(due(500), 1);
+        assert_eq!(due(529), 1);
+        assert_eq!(due(530), 2);
+        assert_eq!(due(1100), 21);
+
+        assert_eq!(Repeat::None.due(Duration::from_secs(10)), 0);
+        assert_eq!(Repeat::Passthrough.due(Duration::from_secs(10)), 0);
+    }
+
+    #[test]
+    fn policies_per_slot() {
+        let options: RepeatOptions = ron::from_str(
+            "(default: Timed(delay: Milliseconds(250), interval: Milliseconds(50)), slots: { 2: None })",
+        )
+        .unwrap();
+
+        assert_eq!(options.policy(Some(2)), Repeat::None);
+        assert_eq!(options.policy(Some(1)), options.default);
+        assert_eq!(RepeatOptions::default().policy(None), Repeat::Passthrough);
+    }
+}
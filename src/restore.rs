@@ -0,0 +1,95 @@
+//! Original placement of the windows we took control of, so they can be put
+//! back on exit, or by the `restore` command after a crash.
+
+use std::io::{Read, Write};
+use std::path::Path;
+
+use windows::HWND;
+
+use crate::geometry::Rect;
+use crate::platform::{Platform, WindowStyle};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub hwnd: isize,
+    /// Process of the window and its start time: after a restart, the handle
+    /// may belong to another window
+    #[serde(default)]
+    pub pid: u32,
+    #[serde(default)]
+    pub started: Option<u64>,
+    pub rect: Rect,
+    pub style: isize,
+    pub ex_style: isize,
+}
+
+impl Placement {
+    pub fn capture<P: Platform>(platform: &P, hwnd: HWND) -> Self {
+        let style = platform.window_style(hwnd);
+        let pid = platform.window_process_id(hwnd);
+
+        Self {
+            hwnd: hwnd.0,
+            pid,
+            started: platform.process_start_time(pid),
+            rect: platform.window_rect(hwnd),
+            style: style.style,
+            ex_style: style.ex_style,
+        }
+    }
+
+    /// Whether the window is still the one captured, the same process owns it
+    pub fn is_current<P: Platform>(&self, platform: &P) -> bool {
+        let hwnd = HWND(self.hwnd);
+        let pid = platform.window_process_id(hwnd);
+
+        platform.is_window(hwnd)
+            && pid == self.pid
+            && platform.process_start_time(pid) == self.started
+    }
+
+    pub fn restore<P: Platform>(&self, platform: &P) {
+        let hwnd = HWND(self.hwnd);
+
+        platform.set_window_style(
+            hwnd,
+            WindowStyle {
+                style: self.style,
+                ex_style: self.ex_style,
+            },
+        );
+        platform.move_window(hwnd, self.rect, true);
+    }
+}
+
+pub fn save<P: AsRef<Path>>(path: P, placements: &[Placement]) -> std::io::Result<()> {
+    let contents = ron::to_string(placements)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<Placement>> {
+    let mut file = std::fs::File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    ron::from_str(&contents).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Puts back every window saved at `path` that still exists, then forgets about them.
+/// Returns how many windows were restored.
+pub fn restore_saved<P: Platform, S: AsRef<Path>>(platform: &P, path: S) -> std::io::Result<usize> {
+    let placements = load(&path)?
+        .into_iter()
+        .filter(|p| p.is_current(platform))
+        .collect::<Vec<_>>();
+
+    for placement in &placements {
+        placement.restore(platform);
+    }
+
+    std::fs::remove_file(path)?;
+    Ok(placements.len())
+}
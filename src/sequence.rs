@@ -0,0 +1,231 @@
+//! Remaps that type a sequence of keys for one leader key, like the chat
+//! command `/follow`.
+//!
+//! In the config a sequence is written as a list of chords and delays:
+//! `VK_F: Sequence([VK_RETURN, VK_OEM_2, VK_F, Delay(Milliseconds(50)), VK_RETURN])`.
+
+use std::fmt;
+use std::time::Duration;
+
+use serde::de::{self, EnumAccess, VariantAccess, Visitor};
+use serde::Deserialize;
+
+use crate::chord::{self, Chord, Framing, Name};
+use crate::config::{self, Key};
+
+/// What a follower is sent for a leader chord
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Remap {
+    /// Held for as long as the leader key
+    Chord(Chord),
+    /// Typed once when the leader key is pressed
+    Sequence(Vec<Step>),
+}
+
+impl From<Chord> for Remap {
+    fn from(chord: Chord) -> Self {
+        Remap::Chord(chord)
+    }
+}
+
+impl From<Key> for Remap {
+    fn from(key: Key) -> Self {
+        Remap::Chord(key.into())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Pressed and released, with exactly its own modifiers
+    Tap(Chord),
+    /// Wait before the next step
+    Delay(config::Duration),
+}
+
+/// A keystroke sent to a follower, `at` some time after the leader key event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stroke {
+    pub at: Duration,
+    pub key: Key,
+    pub down: bool,
+    /// Presses the keystroke stands for, more than 1 when autorepeats are
+    /// sent at once
+    pub count: u16,
+}
+
+impl Stroke {
+    /// Sent right away
+    pub fn now(key: Key, down: bool) -> Self {
+        Self {
+            at: Duration::ZERO,
+            key,
+            down,
+            count: 1,
+        }
+    }
+}
+
+/// Timeline of `steps` for a follower holding the `held` modifier keys.
+///
+/// Every tap is framed on its own, so the follower holds the same keys again
+/// once the sequence is over.
+pub fn expand(steps: &[Step], held: &[Key]) -> Vec<Stroke> {
+    let mut at = Duration::ZERO;
+    let mut strokes = Vec::new();
+
+    for step in steps {
+        match step {
+            Step::Tap(chord) => {
+                let framing = Framing::around(held, *chord);
+                let keys = framing
+                    .press(chord.key)
+                    .into_iter()
+                    .chain(framing.release(chord.key, held));
+
+                strokes.extend(keys.map(|(key, down)| Stroke {
+                    at,
+                    ..Stroke::now(key, down)
+                }));
+            }
+            Step::Delay(delay) => at += Duration::from(*delay),
+        }
+    }
+
+    strokes
+}
+
+struct RemapVisitor;
+
+impl<'de> Visitor<'de> for RemapVisitor {
+    type Value = Remap;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a chord, or a Sequence([..]) of chords and delays")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Remap, A::Error> {
+        let (Name(name), variant) = data.variant::<Name>()?;
+
+        match name.as_str() {
+            "Sequence" => Ok(Remap::Sequence(variant.newtype_variant()?)),
+            _ => chord::from_variant(&name, variant).map(Remap::Chord),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Remap {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_enum("Remap", &[], RemapVisitor)
+    }
+}
+
+struct StepVisitor;
+
+impl<'de> Visitor<'de> for StepVisitor {
+    type Value = Step;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a chord or a Delay(..)")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Step, A::Error> {
+        let (Name(name), variant) = data.variant::<Name>()?;
+
+        match name.as_str() {
+            "Delay" => Ok(Step::Delay(variant.newtype_variant()?)),
+            _ => chord::from_variant(&name, variant).map(Step::Tap),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Step {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_enum("Step", &[], StepVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chord::Modifiers;
+    use Key::*;
+
+    const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        ..Modifiers::NONE
+    };
+
+    #[test]
+    fn parses_chords_and_sequences() {
+        let remaps: Vec<Remap> = ron::from_str(
+            "[Alt(VK_2), Sequence([VK_RETURN, Shift(VK_1), Delay(Milliseconds(50)), VK_RETURN])]",
+        )
+        .unwrap();
+
+        assert_eq!(
+            remaps,
+            vec![
+                Remap::Chord(Chord::new(
+                    Modifiers {
+                        alt: true,
+                        ..Modifiers::NONE
+                    },
+                    VK_2
+                )),
+                Remap::Sequence(vec![
+                    Step::Tap(VK_RETURN.into()),
+                    Step::Tap(Chord::new(SHIFT, VK_1)),
+                    Step::Delay(config::Duration::Milliseconds(50)),
+                    Step::Tap(VK_RETURN.into()),
+                ])
+            ]
+        );
+        assert!(ron::from_str::<Remap>("Sequence([Wait(5)])").is_err());
+    }
+
+    #[test]
+    fn expands_into_a_timeline() {
+        let steps = [
+            Step::Tap(VK_RETURN.into()),
+            Step::Delay(config::Duration::Milliseconds(50)),
+            Step::Tap(Chord::new(SHIFT, VK_1)),
+            Step::Delay(config::Duration::Seconds(1)),
+            Step::Tap(VK_RETURN.into()),
+        ];
+        let at = |ms, key, down| Stroke {
+            at: Duration::from_millis(ms),
+            ..Stroke::now(key, down)
+        };
+
+        assert_eq!(
+            expand(&steps, &[]),
+            vec![
+                at(0, VK_RETURN, true),
+                at(0, VK_RETURN, false),
+                at(50, VK_LSHIFT, true),
+                at(50, VK_1, true),
+                at(50, VK_1, false),
+                at(50, VK_LSHIFT, false),
+                at(1050, VK_RETURN, true),
+                at(1050, VK_RETURN, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn taps_lift_the_modifiers_held_by_the_follower() {
+        let steps = [Step::Tap(VK_F.into()), Step::Tap(Chord::new(SHIFT, VK_F))];
+
+        assert_eq!(
+            expand(&steps, &[VK_RSHIFT]),
+            vec![
+                Stroke::now(VK_RSHIFT, false),
+                Stroke::now(VK_F, true),
+                Stroke::now(VK_F, false),
+                Stroke::now(VK_RSHIFT, true),
+                Stroke::now(VK_F, true),
+                Stroke::now(VK_F, false),
+            ]
+        );
+    }
+}
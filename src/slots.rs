@@ -0,0 +1,349 @@
+//! Stable slot numbers for clients, so the same character always gets the
+//! same screen position. Slot 0 is the leader.
+
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Gives `slot` to the window whose title contains `title` (case-insensitive)
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SlotRule {
+    pub title: String,
+    pub slot: usize,
+}
+
+/// Order in which windows without a rule or a remembered slot fill the free slots
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SlotOrder {
+    /// Whatever order the system lists the windows in
+    #[default]
+    Enumeration,
+    /// Oldest client process first
+    ProcessStart,
+    /// Lowest process id first
+    Pid,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SlotOptions {
+    #[serde(default)]
+    pub rules: Vec<SlotRule>,
+    #[serde(default)]
+    pub order: SlotOrder,
+    /// Where the slots are remembered between runs, never when unset
+    #[serde(default = "default_slot_file")]
+    pub file: Option<PathBuf>,
+}
+
+pub fn default_slot_file() -> Option<PathBuf> {
+    Some(std::env::temp_dir().join("is_boxer_like_slots.ron"))
+}
+
+impl Default for SlotOptions {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            order: SlotOrder::default(),
+            file: default_slot_file(),
+        }
+    }
+}
+
+/// A window waiting for a slot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotCandidate {
+    pub title: String,
+    pub pid: u32,
+    /// Creation time of the process, in any unit that sorts chronologically
+    pub started: Option<u64>,
+}
+
+/// Which client held a slot last time
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SlotRecord {
+    pub slot: usize,
+    pub title: String,
+    /// Process of the client and its start time, pids are reused
+    pub pid: u32,
+    #[serde(default)]
+    pub started: Option<u64>,
+}
+
+impl SlotRecord {
+    fn same_process(&self, pid: u32, started: Option<u64>) -> bool {
+        self.pid == pid && self.started == started
+    }
+}
+
+/// Gives every candidate a slot that is not in `taken`, in this order:
+///
+/// 1. the first rule matching its title,
+/// 2. the slot it had last time, recognized by its process, then by its title,
+/// 3. the lowest free slot, following `options.order`.
+///
+/// A slot is never handed out twice, the first candidate asking for it wins.
+pub fn assign_slots(
+    options: &SlotOptions,
+    remembered: &[SlotRecord],
+    taken: &[usize],
+    candidates: &[SlotCandidate],
+) -> Vec<usize> {
+    let mut taken = taken.iter().copied().collect::<BTreeSet<_>>();
+    let mut slots = vec![None; candidates.len()];
+
+    let mut claim = |slots: &mut Vec<Option<usize>>,
+                     pick: &dyn Fn(&SlotCandidate) -> Option<usize>| {
+        for (candidate, slot) in candidates.iter().zip(slots.iter_mut()) {
+            if slot.is_some() {
+                continue;
+            }
+            if let Some(wanted) = pick(candidate).filter(|s| !taken.contains(s)) {
+                taken.insert(wanted);
+                *slot = Some(wanted);
+            }
+        }
+    };
+
+    claim(&mut slots, &|candidate| {
+        let title = candidate.title.to_lowercase();
+        options
+            .rules
+            .iter()
+            .find(|rule| title.contains(&rule.title.to_lowercase()))
+            .map(|rule| rule.slot)
+    });
+    claim(&mut slots, &|candidate| {
+        remembered
+            .iter()
+            .find(|record| record.same_process(candidate.pid, candidate.started))
+            .map(|record| record.slot)
+    });
+    claim(&mut slots, &|candidate| {
+        remembered
+            .iter()
+            .find(|record| record.title == candidate.title && !taken_by_process(record, candidates))
+            .map(|record| record.slot)
+    });
+
+    let mut rest = (0..candidates.len())
+        .filter(|&i| slots[i].is_none())
+        .collect::<Vec<_>>();
+    match options.order {
+        SlotOrder::Enumeration => {}
+        SlotOrder::ProcessStart => {
+            rest.sort_by_key(|&i| (candidates[i].started.is_none(), candidates[i].started))
+        }
+        SlotOrder::Pid => rest.sort_by_key(|&i| candidates[i].pid),
+    }
+
+    let mut free = (0..).filter(|slot| !taken.contains(slot));
+    for i in rest {
+        slots[i] = free.next();
+    }
+
+    slots.into_iter().map(Option::unwrap_or_default).collect()
+}
+
+/// A record whose process is still around belongs to that process, not to its title
+fn taken_by_process(record: &SlotRecord, candidates: &[SlotCandidate]) -> bool {
+    candidates
+        .iter()
+        .any(|c| record.same_process(c.pid, c.started))
+}
+
+/// Replaces the records of the given slots and processes, and keeps the
+/// others so an offline client still finds its slot when it comes back
+pub fn remember(remembered: &mut Vec<SlotRecord>, current: Vec<SlotRecord>) {
+    remembered.retain(|old| {
+        !current
+            .iter()
+            .any(|new| new.slot == old.slot || new.same_process(old.pid, old.started))
+    });
+    remembered.extend(current);
+    remembered.sort_by_key(|record| record.slot);
+}
+
+pub fn save<P: AsRef<Path>>(path: P, records: &[SlotRecord]) -> std::io::Result<()> {
+    let contents = ron::to_string(records)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<SlotRecord>> {
+    let mut file = std::fs::File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    ron::from_str(&contents).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(title: &str, pid: u32, started: u64) -> SlotCandidate {
+        SlotCandidate {
+            title: title.into(),
+            pid,
+            started: Some(started),
+        }
+    }
+
+    fn options(order: SlotOrder) -> SlotOptions {
+        SlotOptions {
+            rules: Vec::new(),
+            order,
+            file: None,
+        }
+    }
+
+    #[test]
+    fn fills_free_slots_in_the_configured_order() {
+        let candidates = [
+            candidate("World of Warcraft", 30, 1),
+            candidate("World of Warcraft", 10, 3),
+            candidate("World of Warcraft", 20, 2),
+        ];
+
+        let assign = |order| assign_slots(&options(order), &[], &[], &candidates);
+        assert_eq!(assign(SlotOrder::Enumeration), vec![0, 1, 2]);
+        assert_eq!(assign(SlotOrder::ProcessStart), vec![0, 2, 1]);
+        assert_eq!(assign(SlotOrder::Pid), vec![2, 0, 1]);
+
+        assert_eq!(
+            assign_slots(&options(SlotOrder::Pid), &[], &[0, 2], &candidates),
+            vec![4, 1, 3]
+        );
+    }
+
+    #[test]
+    fn rules_come_before_memory() {
+        let options = SlotOptions {
+            rules: vec![
+                SlotRule {
+                    title: "healer".into(),
+                    slot: 3,
+                },
+                SlotRule {
+                    title: "tank".into(),
+                    slot: 0,
+                },
+            ],
+            ..options(SlotOrder::Enumeration)
+        };
+        let remembered = [SlotRecord {
+            slot: 0,
+            title: "WoW - Mage".into(),
+            pid: 7,
+            started: Some(0),
+        }];
+        let candidates = [
+            candidate("WoW - Mage", 7, 0),
+            candidate("WoW - Healer", 8, 0),
+            candidate("WoW - Tank", 9, 0),
+        ];
+
+        assert_eq!(
+            assign_slots(&options, &remembered, &[], &candidates),
+            vec![1, 3, 0]
+        );
+    }
+
+    #[test]
+    fn remembers_slots_by_process_then_title() {
+        let remembered = [
+            SlotRecord {
+                slot: 2,
+                title: "WoW - Healer".into(),
+                pid: 5,
+                started: Some(0),
+            },
+            SlotRecord {
+                slot: 1,
+                title: "WoW - Mage".into(),
+                pid: 6,
+                started: Some(0),
+            },
+        ];
+        // The healer was relaunched, the mage kept running and was renamed
+        let candidates = [
+            candidate("WoW - Rogue", 40, 0),
+            candidate("WoW - Healer", 41, 0),
+            candidate("WoW", 6, 0),
+        ];
+
+        assert_eq!(
+            assign_slots(
+                &options(SlotOrder::Enumeration),
+                &remembered,
+                &[],
+                &candidates
+            ),
+            vec![0, 2, 1]
+        );
+    }
+
+    #[test]
+    fn reused_pids_are_not_the_same_process() {
+        let remembered = [
+            SlotRecord {
+                slot: 2,
+                title: "WoW - Healer".into(),
+                pid: 5,
+                started: Some(100),
+            },
+            SlotRecord {
+                slot: 1,
+                title: "WoW - Mage".into(),
+                pid: 6,
+                started: Some(100),
+            },
+        ];
+        // After a reboot the mage got the healer's old pid
+        let candidates = [
+            candidate("WoW - Mage", 5, 200),
+            candidate("WoW - Healer", 41, 210),
+        ];
+
+        assert_eq!(
+            assign_slots(
+                &options(SlotOrder::Enumeration),
+                &remembered,
+                &[],
+                &candidates
+            ),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn remember_keeps_offline_clients() {
+        let record = |slot, title: &str, pid| SlotRecord {
+            slot,
+            title: title.into(),
+            pid,
+            started: Some(0),
+        };
+        let mut remembered = vec![
+            record(0, "Mage", 5),
+            record(2, "Tank", 1),
+            record(3, "Healer", 2),
+        ];
+
+        remember(
+            &mut remembered,
+            vec![record(0, "Mage", 5), record(1, "Tank", 1)],
+        );
+
+        assert_eq!(
+            remembered,
+            vec![
+                record(0, "Mage", 5),
+                record(1, "Tank", 1),
+                record(3, "Healer", 2)
+            ]
+        );
+    }
+}
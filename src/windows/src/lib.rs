@@ -1,5 +1,5 @@
-pub use windows::core::PCSTR;
-pub use windows::Win32::Foundation::{BOOL, HWND, LPARAM, POINT, RECT, WPARAM};
+pub use windows::core::{PCSTR, PSTR};
+pub use windows::Win32::Foundation::{BOOL, FILETIME, HANDLE, HWND, LPARAM, POINT, RECT, WPARAM};
 
 // Window Styles
 pub mod styles {
@@ -12,16 +12,21 @@ pub mod styles {
 
 // Windowing
 pub mod windowing {
+    pub use windows::Win32::Graphics::Gdi::ClientToScreen;
     pub use windows::Win32::UI::WindowsAndMessaging::{
         FindWindowA as FindWindow,
+        GetClassNameA as GetClassName,
         GetClientRect,
         GetCursorPos,
         GetForegroundWindow, // Forces clippy to put them once per line
+        GetWindow,
         GetWindowLongPtrA as GetWindowLong,
         GetWindowRect,
         GetWindowTextA as GetWindowText,
         GetWindowTextLengthA as GetWindowTextLength,
         GetWindowThreadProcessId,
+        IsWindow,
+        IsWindowVisible,
         MoveWindow,
         PostMessageA as PostMessage,
         SendMessageA as SendMessage,
@@ -33,33 +38,57 @@ pub mod windowing {
     };
 
     pub use windows::Win32::UI::WindowsAndMessaging::{
-        SHOW_WINDOW_CMD, SPI_GETWORKAREA, SWP_FRAMECHANGED, SWP_NOMOVE, SWP_NOOWNERZORDER,
+        GW_OWNER, SHOW_WINDOW_CMD, SPI_GETWORKAREA, SWP_FRAMECHANGED, SWP_NOMOVE, SWP_NOOWNERZORDER,
         SWP_NOSIZE, SWP_NOZORDER, SWP_SHOWWINDOW, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
         WM_NCACTIVATE,
     };
 }
 
+pub mod monitor {
+    pub use windows::Win32::Graphics::Gdi::{
+        EnumDisplayMonitors, GetMonitorInfoA as GetMonitorInfo, HDC, HMONITOR, MONITORINFO,
+    };
+    pub use windows::Win32::UI::WindowsAndMessaging::MONITORINFOF_PRIMARY;
+}
+
 pub mod messaging {
     pub use windows::Win32::UI::WindowsAndMessaging::{
         HTCLIENT, HWND_NOTOPMOST, HWND_TOP, HWND_TOPMOST, KF_REPEAT, MA_ACTIVATE, SW_HIDE,
         SW_RESTORE, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN,
         WM_MBUTTONUP, WM_MOUSEACTIVATE, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_PARENTNOTIFY,
-        WM_RBUTTONDOWN, WM_RBUTTONUP,
+        WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
     };
 }
 
 pub mod vk {
     // pub use windows::Win32::UI::Input::KeyboardAndMouseInput::*;
-    pub use windows::Win32::System::SystemServices::{MK_LBUTTON, MK_MBUTTON, MK_RBUTTON};
+    pub use windows::Win32::System::SystemServices::{
+        MK_CONTROL, MK_LBUTTON, MK_MBUTTON, MK_RBUTTON, MK_SHIFT,
+    };
     pub use windows::Win32::UI::Input::KeyboardAndMouse::*;
     pub use windows::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, GetKeyboardState};
 }
 
+pub mod hooks {
+    pub use windows::Win32::Foundation::{HMODULE, LRESULT};
+    pub use windows::Win32::UI::WindowsAndMessaging::{
+        CallNextHookEx, GetMessageW as GetMessage, PostThreadMessageW as PostThreadMessage,
+        SetWindowsHookExW as SetWindowsHookEx, UnhookWindowsHookEx, HC_ACTION, HHOOK,
+        KBDLLHOOKSTRUCT, LLKHF_INJECTED, LLMHF_INJECTED, MSG, MSLLHOOKSTRUCT, WH_KEYBOARD_LL,
+        WH_MOUSE_LL, WM_QUIT,
+    };
+}
+
 pub mod threading {
     pub use windows::Win32::System::Threading::{AttachThreadInput, GetCurrentThreadId};
     pub use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
 }
 
 pub mod process {
+    pub use windows::Win32::Foundation::CloseHandle;
+    pub use windows::Win32::System::Threading::{
+        GetProcessTimes, OpenProcess, QueryFullProcessImageNameA as QueryFullProcessImageName,
+        PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
     pub use windows::Win32::UI::WindowsAndMessaging::EnumWindows;
 }